@@ -0,0 +1,197 @@
+use crate::column_store::{ProcessedData, SensorColumn};
+use crate::csv_processor::is_named_file;
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How secondary files are placed on the reference file's timeline.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignMode {
+    /// Rows are joined only on identical timestamps.
+    #[default]
+    Exact,
+    /// The closest sample in either direction.
+    Nearest,
+    /// The last sample at or before the reference timestamp.
+    Backward,
+    /// Linear interpolation between the samples either side.
+    Linear,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Alignment {
+    pub mode: AlignMode,
+    /// Largest distance in milliseconds between a reference timestamp and the sample
+    /// used for it (for `Linear`, between the two samples interpolated). Unlimited when unset.
+    #[serde(rename = "maxGapMs")]
+    pub max_gap_ms: Option<u64>,
+    /// File whose timestamps form the timeline (full path or file name); the first
+    /// input when unset.
+    pub reference: Option<String>,
+}
+
+/// One sensor's valid samples, sorted by time in microseconds, with their quality.
+struct Samples {
+    times: Vec<i64>,
+    values: Vec<f64>,
+    bad: Vec<bool>,
+}
+
+impl Samples {
+    fn of(timestamps: &[DateTime<Utc>], column: &SensorColumn) -> Self {
+        let mut points: Vec<(i64, f64, bool)> = timestamps
+            .iter()
+            .enumerate()
+            .filter_map(|(row, ts)| {
+                Some((ts.timestamp_micros(), column.get(row)?, column.is_bad(row)))
+            })
+            .collect();
+        // Stable, so a repeated timestamp keeps file order and the later sample wins below
+        points.sort_by_key(|p| p.0);
+        let mut samples = Samples {
+            times: Vec::with_capacity(points.len()),
+            values: Vec::with_capacity(points.len()),
+            bad: Vec::with_capacity(points.len()),
+        };
+        for (t, v, bad) in points {
+            if samples.times.last() == Some(&t) {
+                *samples.values.last_mut().expect("values track times") = v;
+                *samples.bad.last_mut().expect("flags track times") = bad;
+            } else {
+                samples.times.push(t);
+                samples.values.push(v);
+                samples.bad.push(bad);
+            }
+        }
+        samples
+    }
+
+    fn sample(&self, i: usize) -> (f64, bool) {
+        (self.values[i], self.bad[i])
+    }
+
+    /// The value for `t` and whether it is bad; interpolated values are bad when
+    /// either sample is.
+    fn value_at(&self, t: i64, mode: AlignMode, max_gap: i64) -> Option<(f64, bool)> {
+        // Samples before `after` are at or before t
+        let after = self.times.partition_point(|&s| s <= t);
+        let before = after.checked_sub(1);
+        match mode {
+            AlignMode::Exact => before
+                .filter(|&i| self.times[i] == t)
+                .map(|i| self.sample(i)),
+            AlignMode::Backward => before
+                .filter(|&i| t - self.times[i] <= max_gap)
+                .map(|i| self.sample(i)),
+            AlignMode::Nearest => {
+                let back = before.map(|i| (t - self.times[i], i));
+                let ahead = (after < self.times.len()).then(|| (self.times[after] - t, after));
+                // Ties go to the earlier sample
+                let (gap, i) = match (back, ahead) {
+                    (Some(b), Some(a)) => {
+                        if a.0 < b.0 {
+                            a
+                        } else {
+                            b
+                        }
+                    }
+                    (Some(b), None) => b,
+                    (None, Some(a)) => a,
+                    (None, None) => return None,
+                };
+                (gap <= max_gap).then(|| self.sample(i))
+            }
+            AlignMode::Linear => {
+                let i = before?;
+                if self.times[i] == t {
+                    return Some(self.sample(i));
+                }
+                if after >= self.times.len() {
+                    return None;
+                }
+                let (t0, t1) = (self.times[i], self.times[after]);
+                if t1 - t0 > max_gap {
+                    return None;
+                }
+                let fraction = (t - t0) as f64 / (t1 - t0) as f64;
+                Some((
+                    self.values[i] + (self.values[after] - self.values[i]) * fraction,
+                    self.bad[i] || self.bad[after],
+                ))
+            }
+        }
+    }
+}
+
+/// Resamples every dataset except the reference onto the reference's timestamps, so
+/// the following merge joins them row for row. `Exact` leaves the datasets untouched.
+pub fn align_datasets(
+    datasets: Vec<ProcessedData>,
+    sources: &[String],
+    alignment: &Alignment,
+) -> Result<Vec<ProcessedData>, String> {
+    if alignment.mode == AlignMode::Exact || datasets.len() < 2 {
+        return Ok(datasets);
+    }
+    let reference = match &alignment.reference {
+        Some(file) => sources
+            .iter()
+            .position(|source| is_named_file(source, file))
+            .ok_or_else(|| format!("Reference file {} is not among the inputs", file))?,
+        None => 0,
+    };
+
+    let mut timeline = datasets[reference].timestamps.clone();
+    timeline.par_sort_unstable();
+    timeline.dedup();
+    let micros: Vec<i64> = timeline.iter().map(|t| t.timestamp_micros()).collect();
+    let max_gap = alignment
+        .max_gap_ms
+        .map_or(i64::MAX, |ms| (ms as i64).saturating_mul(1000));
+
+    let aligned = datasets
+        .into_iter()
+        .enumerate()
+        .map(|(d, ds)| {
+            if d == reference {
+                return ds;
+            }
+            let columns = ds
+                .columns
+                .par_iter()
+                .map(|column| {
+                    let samples = Samples::of(&ds.timestamps, column);
+                    // States change in steps; interpolating their codes is meaningless
+                    let mode = match alignment.mode {
+                        AlignMode::Linear if column.is_categorical() => AlignMode::Backward,
+                        mode => mode,
+                    };
+                    let mut aligned = column.empty_like(micros.len());
+                    for &t in &micros {
+                        match samples.value_at(t, mode, max_gap) {
+                            Some((value, bad)) => aligned.push_sample(Some(value), bad),
+                            None => aligned.push(None),
+                        }
+                    }
+                    aligned
+                })
+                .collect();
+            ProcessedData {
+                timestamp_header: ds.timestamp_header,
+                timestamps: timeline.clone(),
+                columns,
+            }
+        })
+        .collect();
+
+    println!(
+        "Aligned {} files onto {} ({} timestamps, {:?})",
+        sources.len() - 1,
+        sources[reference],
+        timeline.len(),
+        alignment.mode
+    );
+    Ok(aligned)
+}
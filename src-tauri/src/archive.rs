@@ -0,0 +1,108 @@
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use zip::{CompressionMethod, ZipArchive};
+
+/// Separates a zip archive path from the member inside it, as in
+/// `exports/2023.zip!/line1/pumps.csv`.
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+fn has_extension(name: &str, ext: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
+
+/// File name with any `.gz`/`.zst` suffix removed, so `pumps.csv.gz` is recognised as CSV.
+pub(crate) fn inner_name(name: &str) -> &str {
+    if has_extension(name, "gz") || has_extension(name, "zst") {
+        &name[..name.rfind('.').unwrap_or(name.len())]
+    } else {
+        name
+    }
+}
+
+fn is_csv_member(name: &str) -> bool {
+    !name.ends_with('/') && has_extension(inner_name(name), "csv")
+}
+
+/// Replaces every `.zip` input with one path per CSV member of the archive,
+/// in archive order. Other paths are passed through unchanged.
+pub fn expand_archives(paths: &[String]) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !has_extension(path, "zip") {
+            expanded.push(path.clone());
+            continue;
+        }
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let archive =
+            ZipArchive::new(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?;
+        let before = expanded.len();
+        expanded.extend(
+            archive
+                .file_names()
+                .filter(|name| is_csv_member(name))
+                .map(|name| format!("{}{}{}", path, MEMBER_SEPARATOR, name)),
+        );
+        if expanded.len() == before {
+            return Err(format!("{}: archive contains no CSV files", path));
+        }
+    }
+    Ok(expanded)
+}
+
+/// Streams one member of a zip archive. A member reader cannot outlive its archive,
+/// so the member's compressed bytes are read and inflated straight from the file
+/// instead; its checksum is not verified.
+fn open_member(archive_path: &str, member: &str) -> Result<Box<dyn Read + Send>, String> {
+    let file = File::open(archive_path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    let entry = archive.by_name(member).map_err(|e| e.to_string())?;
+    if entry.encrypted() {
+        return Err("encrypted members are not supported".to_string());
+    }
+    let (start, size, method) = (
+        entry.data_start(),
+        entry.compressed_size(),
+        entry.compression(),
+    );
+    drop(entry);
+
+    let mut reader = archive.into_inner();
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(|e| e.to_string())?;
+    let data = reader.take(size);
+    match method {
+        CompressionMethod::Stored => Ok(Box::new(data)),
+        CompressionMethod::Deflated => Ok(Box::new(DeflateDecoder::new(data))),
+        CompressionMethod::Zstd => Ok(Box::new(
+            zstd::Decoder::with_buffer(data).map_err(|e| e.to_string())?,
+        )),
+        method => Err(format!("unsupported compression method {}", method)),
+    }
+}
+
+/// Opens an input for reading, decompressing `.gz` and `.zst` files on the fly.
+/// Zip members are addressed as `archive.zip!/member.csv` and are streamed from the
+/// archive rather than inflated into memory.
+pub fn open_input(path: &str) -> Result<Box<dyn Read + Send>, String> {
+    let (raw, name): (Box<dyn Read + Send>, &str) = match path.rsplit_once(MEMBER_SEPARATOR) {
+        Some((archive_path, member)) if has_extension(archive_path, "zip") => (
+            open_member(archive_path, member).map_err(|e| format!("{}: {}", path, e))?,
+            member,
+        ),
+        _ => (Box::new(File::open(path).map_err(|e| e.to_string())?), path),
+    };
+
+    if has_extension(name, "gz") {
+        Ok(Box::new(MultiGzDecoder::new(BufReader::new(raw))))
+    } else if has_extension(name, "zst") {
+        let decoder = zstd::Decoder::new(raw).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Box::new(decoder))
+    } else {
+        Ok(raw)
+    }
+}
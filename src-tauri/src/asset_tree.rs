@@ -0,0 +1,116 @@
+use crate::column_store::SensorColumn;
+use crate::metadata::MetadataStore;
+use serde::Serialize;
+
+/// Node of the equipment hierarchy built from metadata component paths such as
+/// `Plant/Unit2/Boiler/FeedPump`. Segments are matched ignoring case and keep the
+/// spelling they were first seen with; children are sorted by name.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct AssetNode {
+    pub name: String,
+    /// Segments from the root joined by `/`; empty for the root.
+    pub path: String,
+    /// Sensors whose component is this node.
+    pub sensors: Vec<String>,
+    pub children: Vec<AssetNode>,
+}
+
+/// A node without its descendants, for browsing one level at a time.
+#[derive(Debug, Serialize, Clone)]
+pub struct AssetSummary {
+    pub name: String,
+    pub path: String,
+    pub child_count: usize,
+    /// Sensors at this node and below.
+    pub sensor_count: usize,
+}
+
+/// Splits a component path on `/` or `\`, dropping empty segments.
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '\\'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+impl AssetNode {
+    /// Places each sensor under the metadata component of its tag. Sensors without
+    /// metadata or without a component are left out.
+    pub fn build(sensors: &[SensorColumn], metadata: &MetadataStore) -> AssetNode {
+        let mut root = AssetNode::default();
+        for sensor in sensors {
+            let Some(component) = metadata.get(sensor.tag()).map(|m| m.component.as_str()) else {
+                continue;
+            };
+            let mut node = &mut root;
+            let mut depth = 0;
+            for segment in segments(component) {
+                let i = match node
+                    .children
+                    .iter()
+                    .position(|c| c.name.to_lowercase() == segment.to_lowercase())
+                {
+                    Some(i) => i,
+                    None => {
+                        let path = if node.path.is_empty() {
+                            segment.to_string()
+                        } else {
+                            format!("{}/{}", node.path, segment)
+                        };
+                        node.children.push(AssetNode {
+                            name: segment.to_string(),
+                            path,
+                            ..Default::default()
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[i];
+                depth += 1;
+            }
+            if depth > 0 {
+                node.sensors.push(sensor.name.clone());
+            }
+        }
+        root.sort();
+        root
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by_key(|c| c.name.to_lowercase());
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+
+    /// The node at `path`, compared segment by segment ignoring case; the root for an
+    /// empty path.
+    pub fn find(&self, path: &str) -> Option<&AssetNode> {
+        segments(path).try_fold(self, |node, segment| {
+            node.children
+                .iter()
+                .find(|c| c.name.to_lowercase() == segment.to_lowercase())
+        })
+    }
+
+    /// Sensors at this node and below, depth first.
+    pub fn all_sensors(&self) -> Vec<String> {
+        let mut sensors = self.sensors.clone();
+        for child in &self.children {
+            sensors.extend(child.all_sensors());
+        }
+        sensors
+    }
+
+    fn sensor_count(&self) -> usize {
+        self.sensors.len() + self.children.iter().map(Self::sensor_count).sum::<usize>()
+    }
+
+    pub fn summary(&self) -> AssetSummary {
+        AssetSummary {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            child_count: self.children.len(),
+            sensor_count: self.sensor_count(),
+        }
+    }
+}
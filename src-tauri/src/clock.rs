@@ -0,0 +1,109 @@
+use crate::column_store::ProcessedData;
+use crate::csv_processor::{is_named_file, ImportOptions};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Timestamp fixes for one input file, applied before its rows are keyed by time.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FileOptions {
+    /// Milliseconds added to every timestamp of the file.
+    #[serde(rename = "offsetMs")]
+    pub offset_ms: i64,
+    /// IANA zone the file's naive timestamps were recorded in, overriding
+    /// `timestamp.timezone` for this file.
+    pub timezone: Option<String>,
+    pub drift: Option<DriftCorrection>,
+}
+
+/// Linear clock-drift correction: `start_offset_ms` is added at the file's first
+/// timestamp, `end_offset_ms` at its last, and the amount in between is interpolated.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DriftCorrection {
+    #[serde(rename = "startOffsetMs")]
+    pub start_offset_ms: i64,
+    #[serde(rename = "endOffsetMs")]
+    pub end_offset_ms: i64,
+}
+
+/// The correction applied to a file, reported so it can be audited and reused for
+/// rows appended to the file later.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ClockCorrection {
+    pub offset_ms: i64,
+    /// First and last original timestamps the drift was interpolated between.
+    pub drift_span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pub drift_start_ms: i64,
+    pub drift_end_ms: i64,
+}
+
+impl ClockCorrection {
+    pub fn apply(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        let mut shift_ms = self.offset_ms as f64;
+        if let Some((start, end)) = self.drift_span {
+            let span = (end - start).num_microseconds().unwrap_or(0);
+            // Rows past the span, such as ones appended later, extrapolate the same rate
+            let fraction = if span > 0 {
+                (ts - start).num_microseconds().unwrap_or(0) as f64 / span as f64
+            } else {
+                0.0
+            };
+            shift_ms += self.drift_start_ms as f64
+                + (self.drift_end_ms - self.drift_start_ms) as f64 * fraction;
+        }
+        ts + Duration::microseconds((shift_ms * 1000.0).round() as i64)
+    }
+}
+
+/// Per-file options for `path`, matched by full path first, then by file name.
+pub fn file_options<'a>(options: &'a ImportOptions, path: &str) -> Option<&'a FileOptions> {
+    options.file_options.get(path).or_else(|| {
+        options
+            .file_options
+            .iter()
+            .find(|(file, _)| is_named_file(path, file))
+            .map(|(_, file_options)| file_options)
+    })
+}
+
+/// Import options for reading one file, with its timezone override applied.
+pub fn options_for_file(
+    options: &ImportOptions,
+    file_options: Option<&FileOptions>,
+) -> ImportOptions {
+    let mut options = options.clone();
+    if let Some(timezone) = file_options.and_then(|f| f.timezone.clone()) {
+        options.timestamp.timezone = Some(timezone);
+    }
+    options
+}
+
+/// Shifts the timestamps of a freshly read file. Returns the correction applied, if any.
+pub fn correct_timestamps(
+    data: &mut ProcessedData,
+    file_options: &FileOptions,
+) -> Option<ClockCorrection> {
+    let drift_span = match &file_options.drift {
+        Some(_) => {
+            let first = data.timestamps.iter().min()?;
+            let last = data.timestamps.iter().max()?;
+            Some((*first, *last))
+        }
+        None => None,
+    };
+    if file_options.offset_ms == 0 && drift_span.is_none() {
+        return None;
+    }
+    let drift = file_options.drift.clone().unwrap_or_default();
+    let correction = ClockCorrection {
+        offset_ms: file_options.offset_ms,
+        drift_span,
+        drift_start_ms: drift.start_offset_ms,
+        drift_end_ms: drift.end_offset_ms,
+    };
+    for ts in &mut data.timestamps {
+        *ts = correction.apply(*ts);
+    }
+    Some(correction)
+}
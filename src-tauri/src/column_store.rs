@@ -0,0 +1,371 @@
+use crate::states::parse_boolean;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Packed bit set, one bit per row.
+#[derive(Debug, Clone, Default)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    /// A bitmap of `len` cleared bits.
+    pub fn unset(len: usize) -> Self {
+        Bitmap {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Bitmap {
+            words: Vec::with_capacity(capacity.div_ceil(64)),
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        if bit {
+            self.words[self.len / 64] |= 1 << (self.len % 64);
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        i < self.len && (self.words[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, bit: bool) {
+        assert!(i < self.len, "bit index {} out of range {}", i, self.len);
+        if bit {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.words.iter().any(|&w| w != 0)
+    }
+}
+
+/// What the values of a column stand for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    #[default]
+    Numeric,
+    /// 0 for false and 1 for true.
+    Boolean,
+    /// Index of the value's label in the column's state table.
+    State,
+}
+
+/// Labels of a boolean column's two values until the source spells them, e.g. "OFF"
+/// and "ON".
+const BOOLEAN_STATES: [&str; 2] = ["false", "true"];
+
+/// Samples of a single sensor: one contiguous `f64` buffer plus a validity
+/// bitmap marking which rows actually hold a value, and a quality bitmap marking
+/// samples the source flagged as bad.
+#[derive(Debug, Clone, Default)]
+pub struct SensorColumn {
+    pub name: String,
+    /// Tag of the sensor in its source when `name` carries a file prefix; metadata
+    /// is joined on the tag.
+    pub tag: Option<String>,
+    /// Input files the values came from, in load order.
+    pub sources: Vec<String>,
+    pub column_type: ColumnType,
+    /// Label of each value of a boolean or state column, indexed by the value.
+    pub states: Vec<String>,
+    /// Unit written after the values in the source, e.g. "°C".
+    pub unit: Option<String>,
+    values: Vec<f64>,
+    validity: Bitmap,
+    bad: Bitmap,
+}
+
+impl SensorColumn {
+    pub fn new(name: String) -> Self {
+        SensorColumn {
+            name,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_capacity(name: String, capacity: usize) -> Self {
+        SensorColumn {
+            name,
+            values: Vec::with_capacity(capacity),
+            validity: Bitmap::with_capacity(capacity),
+            bad: Bitmap::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// A column of `len` missing values, to be filled with `set_sample`.
+    pub fn missing(name: String, len: usize) -> Self {
+        SensorColumn {
+            name,
+            values: vec![f64::NAN; len],
+            validity: Bitmap::unset(len),
+            bad: Bitmap::unset(len),
+            ..Default::default()
+        }
+    }
+
+    /// An empty column with the name, tag, sources, type, state table and unit of this one.
+    pub fn empty_like(&self, capacity: usize) -> Self {
+        SensorColumn {
+            tag: self.tag.clone(),
+            sources: self.sources.clone(),
+            column_type: self.column_type,
+            states: self.states.clone(),
+            unit: self.unit.clone(),
+            ..SensorColumn::with_capacity(self.name.clone(), capacity)
+        }
+    }
+
+    pub fn with_type(mut self, column_type: ColumnType) -> Self {
+        self.column_type = column_type;
+        self.states = match column_type {
+            ColumnType::Boolean => BOOLEAN_STATES.iter().map(|s| s.to_string()).collect(),
+            _ => Vec::new(),
+        };
+        self
+    }
+
+    /// The sensor's tag in its source: `name` without any file prefix.
+    pub fn tag(&self) -> &str {
+        self.tag.as_deref().unwrap_or(&self.name)
+    }
+
+    /// True for boolean and state columns, whose values are indices into `states`.
+    pub fn is_categorical(&self) -> bool {
+        self.column_type != ColumnType::Numeric
+    }
+
+    /// The value standing for `label`, ignoring case, adding it to the state table if
+    /// it is new.
+    pub fn state_code(&mut self, label: &str) -> f64 {
+        let code = match self
+            .states
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(label))
+        {
+            Some(code) => code,
+            None => {
+                self.states.push(label.to_string());
+                self.states.len() - 1
+            }
+        };
+        code as f64
+    }
+
+    /// The value of a boolean spelling such as "ON" or "no", labelling the value with
+    /// the first spelling seen for it.
+    pub fn boolean_code(&mut self, label: &str) -> Option<f64> {
+        let value = parse_boolean(label)?;
+        let code = value as usize;
+        if self
+            .states
+            .get(code)
+            .is_some_and(|s| s == BOOLEAN_STATES[code])
+        {
+            self.states[code] = label.trim().to_string();
+        }
+        Some(code as f64)
+    }
+
+    /// The code of a boolean column's label in this column's state table: the same
+    /// label, else a label with the same boolean meaning, else a new state. Keeps one
+    /// code per physical state when files spell booleans differently.
+    fn boolean_state_code(&mut self, value: bool, label: &str) -> f64 {
+        if let Some(code) = self
+            .states
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(label))
+        {
+            return code as f64;
+        }
+        match self
+            .states
+            .iter()
+            .position(|s| parse_boolean(s) == Some(value))
+        {
+            Some(code) => {
+                if self.column_type == ColumnType::Boolean
+                    && self.states[code] == BOOLEAN_STATES[code]
+                {
+                    self.states[code] = label.to_string();
+                }
+                code as f64
+            }
+            None => self.state_code(label),
+        }
+    }
+
+    pub fn state_label(&self, i: usize) -> Option<&str> {
+        let code = self.get(i)?;
+        self.states.get(code as usize).map(String::as_str)
+    }
+
+    /// True when one of the columns holds numbers and the other labels, so their
+    /// values cannot share a column. A column without values takes either kind.
+    pub fn kind_conflicts(&self, other: &SensorColumn) -> bool {
+        self.is_categorical() != other.is_categorical()
+            && self.validity.any()
+            && other.validity.any()
+    }
+
+    /// Rewrites the values of a boolean or state column as codes of `target`'s state
+    /// table, adding labels `target` lacks, so both columns can share values. Boolean
+    /// spellings map onto a label of the same meaning, so "ON" and "true" share a code.
+    /// Callers reject columns whose kinds conflict first; a column without values
+    /// takes the kind of the other.
+    pub fn recode_to(&mut self, target: &mut SensorColumn) {
+        if !self.validity.any() {
+            self.column_type = target.column_type;
+            self.states = target.states.clone();
+            return;
+        }
+        if !target.validity.any() && !self.is_categorical() {
+            target.column_type = ColumnType::Numeric;
+            target.states.clear();
+        }
+        if !self.is_categorical() {
+            return;
+        }
+        let column_type = match (target.column_type, self.column_type) {
+            (ColumnType::Numeric, own) => own,
+            (ColumnType::Boolean, ColumnType::Boolean) => ColumnType::Boolean,
+            _ => ColumnType::State,
+        };
+        if target.column_type == ColumnType::Numeric && column_type == ColumnType::Boolean {
+            target.column_type = ColumnType::Boolean;
+            target.states = BOOLEAN_STATES.iter().map(|s| s.to_string()).collect();
+        }
+        let boolean =
+            self.column_type == ColumnType::Boolean || target.column_type == ColumnType::Boolean;
+        let remap: Vec<f64> = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(code, s)| {
+                // A boolean label the column never saw has no samples to recode
+                let unused = || {
+                    self.column_type == ColumnType::Boolean
+                        && !(0..self.values.len()).any(|i| self.get(i) == Some(code as f64))
+                };
+                match parse_boolean(s) {
+                    Some(_) if unused() => f64::NAN,
+                    Some(value) if boolean => target.boolean_state_code(value, s),
+                    _ => target.state_code(s),
+                }
+            })
+            .collect();
+        target.column_type = column_type;
+        for (i, value) in self.values.iter_mut().enumerate() {
+            if self.validity.get(i) {
+                *value = remap[*value as usize];
+            }
+        }
+        self.column_type = target.column_type;
+        self.states = target.states.clone();
+    }
+
+    pub fn push(&mut self, value: Option<f64>) {
+        self.push_sample(value, false);
+    }
+
+    /// Appends a value along with its quality. Bad samples may have no value, when
+    /// the source gave a bad-value token instead of a reading. NaN is stored as missing.
+    pub fn push_sample(&mut self, value: Option<f64>, bad: bool) {
+        let value = value.filter(|v| !v.is_nan());
+        self.values.push(value.unwrap_or(f64::NAN));
+        self.validity.push(value.is_some());
+        self.bad.push(bad);
+    }
+
+    /// Appends the samples of `other`, whose values must already be coded like this
+    /// column's, e.g. by `recode_to`.
+    pub fn extend_from(&mut self, other: &SensorColumn) {
+        for i in 0..other.values.len() {
+            self.push_sample(other.get(i), other.is_bad(i));
+        }
+    }
+
+    pub fn set_sample(&mut self, i: usize, value: Option<f64>, bad: bool) {
+        let value = value.filter(|v| !v.is_nan());
+        self.values[i] = value.unwrap_or(f64::NAN);
+        self.validity.set(i, value.is_some());
+        self.bad.set(i, bad);
+    }
+
+    pub fn mark_bad(&mut self, i: usize) {
+        self.bad.set(i, true);
+    }
+
+    pub fn is_bad(&self, i: usize) -> bool {
+        self.bad.get(i)
+    }
+
+    /// True when any sample is flagged bad.
+    pub fn has_bad(&self) -> bool {
+        self.bad.any()
+    }
+
+    pub fn add_source(&mut self, source: &str) {
+        if !self.sources.iter().any(|s| s == source) {
+            self.sources.push(source.to_string());
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<f64> {
+        if self.validity.get(i) {
+            Some(self.values[i])
+        } else {
+            None
+        }
+    }
+
+    /// The value at `i` unless it is flagged bad.
+    pub fn get_good(&self, i: usize) -> Option<f64> {
+        if self.is_bad(i) {
+            None
+        } else {
+            self.get(i)
+        }
+    }
+}
+
+/// Column-major session data: a single sorted timestamp column shared by all sensors.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedData {
+    /// Name of the source timestamp column, reported first in `headers()`.
+    pub timestamp_header: String,
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub columns: Vec<SensorColumn>,
+}
+
+impl ProcessedData {
+    /// Header list in the layout the frontend expects: timestamp first, then sensors.
+    pub fn headers(&self) -> Vec<String> {
+        std::iter::once(self.timestamp_header.clone())
+            .chain(self.columns.iter().map(|c| c.name.clone()))
+            .collect()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+}
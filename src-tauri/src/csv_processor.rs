@@ -1,16 +1,68 @@
+use crate::align::{self, Alignment};
+use crate::archive;
+use crate::clock::{self, ClockCorrection, FileOptions};
+use crate::column_store::{Bitmap, ColumnType, ProcessedData, SensorColumn};
+use crate::parquet_io;
+use crate::quality::QualityOptions;
+use crate::select::{ColumnFilter, ColumnSelector};
+use crate::states::{infer_column_type, parse_boolean};
+use crate::xlsx_reader;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use encoding_rs::Encoding;
+use encoding_rs_io::DecodeReaderBytesBuilder;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
+use std::borrow::Cow;
+use std::io::{BufRead, BufReader, Cursor, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CsvRecord {
-    pub timestamp: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
     pub values: Vec<Option<f64>>,
+    /// Positions in `values` of samples flagged bad.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bad: Vec<usize>,
 }
 
+impl CsvRecord {
+    /// Row `row` of the columns at `indices`; unknown columns give empty values. Bad
+    /// samples are left out when `exclude_bad` is set and listed in `bad` otherwise.
+    pub fn of_row(
+        data: &ProcessedData,
+        indices: &[Option<usize>],
+        row: usize,
+        exclude_bad: bool,
+    ) -> Self {
+        let mut bad = Vec::new();
+        let values = indices
+            .iter()
+            .enumerate()
+            .map(|(i, idx)| {
+                let column = &data.columns[(*idx)?];
+                if column.is_bad(row) {
+                    if exclude_bad {
+                        return None;
+                    }
+                    bad.push(i);
+                }
+                column.get(row)
+            })
+            .collect();
+        CsvRecord {
+            timestamp: Some(data.timestamps[row]),
+            values,
+            bad,
+        }
+    }
+}
+
+/// Row-oriented slice of session data, as streamed to the frontend.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ProcessedData {
+pub struct DataChunk {
     pub headers: Vec<String>,
     pub rows: Vec<CsvRecord>,
 }
@@ -19,274 +71,2018 @@ pub struct ProcessedData {
 pub struct CsvMetadata {
     pub headers: Vec<String>,
     pub total_rows: usize,
+    pub report: IngestReport,
 }
 
+/// How timestamp strings in the source files should be interpreted.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimestampOptions {
+    /// IANA name of the zone that naive (offset-less) timestamps were recorded in.
+    /// Defaults to UTC.
+    pub timezone: Option<String>,
+    /// Forces `dd/mm/yyyy` (true) or `mm/dd/yyyy` (false) for slashed dates.
+    /// When unset the order is inferred from the data, falling back to day-first.
+    #[serde(rename = "dayFirst")]
+    pub day_first: Option<bool>,
+    /// Header of the timestamp column. Detected from the headers and values when unset.
+    pub column: Option<String>,
+    /// Headers of separate date and time columns to join into the timestamp (CSV only).
+    #[serde(rename = "dateColumn")]
+    pub date_column: Option<String>,
+    #[serde(rename = "timeColumn")]
+    pub time_column: Option<String>,
+}
+
+/// CSV dialect and encoding of the files being imported.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SensorMetadata {
-    pub tag: String,
-    pub description: String,
-    pub unit: String,
-    pub component: String,
+#[serde(default)]
+pub struct ImportOptions {
+    pub delimiter: char,
+    pub quote: char,
+    /// Decimal separator used by numeric cells, e.g. `,` for "12,5".
+    #[serde(rename = "decimalSeparator")]
+    pub decimal_separator: char,
+    /// Digit grouping separator of numeric cells, e.g. `.` for "1.234,5" or `,` for
+    /// "1,234.5". A space also accepts no-break spaces. Digits are not grouped when unset.
+    #[serde(rename = "thousandsSeparator")]
+    pub thousands_separator: Option<char>,
+    /// Cells read as NaN, compared case-insensitively; NaN is stored as a missing value.
+    #[serde(rename = "nanValues")]
+    pub nan_values: Vec<String>,
+    /// Cells read as infinity, compared case-insensitively after an optional sign.
+    #[serde(rename = "infValues")]
+    pub inf_values: Vec<String>,
+    /// Zero-based line index of the header; lines before it are treated as preamble.
+    #[serde(rename = "headerRow")]
+    pub header_row: usize,
+    /// Number of lines directly after the header to discard (unit or description rows).
+    #[serde(rename = "skipLines")]
+    pub skip_lines: usize,
+    /// WHATWG encoding label such as "utf-16le" or "latin1". When unset the
+    /// encoding is taken from the BOM, defaulting to UTF-8.
+    pub encoding: Option<String>,
+    pub timestamp: TimestampOptions,
+    pub layout: TableLayout,
+    /// Columns to load, by exact tag, glob or regex. Every column is loaded when
+    /// empty; the timestamp column is always kept. Other columns are never parsed.
+    pub columns: Vec<ColumnSelector>,
+    /// Quality columns of wide tables, the good quality values of wide and long tables,
+    /// and the bad-value tokens of any table.
+    pub quality: QualityOptions,
+    /// Worksheet to read from Excel workbooks; the first sheet when unset.
+    pub sheet: Option<String>,
+    /// A1-style cell range within the sheet, e.g. "B3:F5000". A single cell
+    /// such as "B3" reads from there to the end of the sheet.
+    #[serde(rename = "cellRange")]
+    pub cell_range: Option<String>,
+    pub conflict: ConflictPolicy,
+    pub alignment: Alignment,
+    pub naming: ColumnNaming,
+    /// Offsets, timezones and drift corrections for individual files, keyed by full
+    /// path or file name.
+    #[serde(rename = "fileOptions")]
+    pub file_options: HashMap<String, FileOptions>,
 }
 
-use rayon::prelude::*;
+/// How columns from different files are named in the merged session.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum ColumnNaming {
+    /// Columns with the same name, ignoring case, are merged into one.
+    #[default]
+    Merge,
+    /// Every column is namespaced by its file name, e.g. `unitA/FlowRate`. Files with
+    /// the same name are told apart by their directory or archive, e.g.
+    /// `site1/unitA/FlowRate`, and failing that by their position in the input list.
+    File,
+    /// Columns of the listed files (keyed by full path or file name) are namespaced by
+    /// the given prefix; other files merge by name.
+    Prefix { prefixes: HashMap<String, String> },
+}
 
-use std::collections::{BTreeMap, HashSet};
+/// How `read_merge_csvs` resolves a cell that more than one file has a value for.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "policy", rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// The earliest file in the input list wins.
+    First,
+    /// Later files overwrite earlier ones.
+    #[default]
+    Last,
+    /// The mean of all values for the cell.
+    Mean,
+    /// The named file (full path or file name) wins; other overlaps resolve as `Last`.
+    Prefer { file: String },
+    /// Abort the import on the first cell where files disagree.
+    Fail,
+}
 
-pub fn read_csv(path: &str) -> Result<ProcessedData, String> {
-    let total_start = Instant::now();
-    // Parse data
-    let file = File::open(path).map_err(|e| e.to_string())?;
+/// Shape of the source table.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum TableLayout {
+    /// One column per sensor.
+    #[default]
+    Wide,
+    /// One row per (timestamp, tag, value), pivoted into one column per tag.
+    /// Files lacking the tag or value column are still read as wide tables,
+    /// so narrow and wide exports can be loaded together.
+    Long(LongLayout),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LongLayout {
+    /// Header of the tag column; "tag", "tagname", "name" or "sensor" when unset.
+    #[serde(rename = "tagColumn")]
+    pub tag_column: Option<String>,
+    /// Header of the value column; "value" or "val" when unset.
+    #[serde(rename = "valueColumn")]
+    pub value_column: Option<String>,
+    /// Optional quality/status column. Rows whose quality is not good by
+    /// `ImportOptions::quality` are dropped, unless `keep_bad_quality` is set.
+    #[serde(rename = "qualityColumn")]
+    pub quality_column: Option<String>,
+    /// Keep rows of bad quality as samples flagged bad instead of dropping them.
+    #[serde(rename = "keepBadQuality")]
+    pub keep_bad_quality: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            delimiter: ',',
+            quote: '"',
+            decimal_separator: '.',
+            thousands_separator: None,
+            nan_values: ["NaN", "NA", "N/A", "null"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            inf_values: ["Inf", "Infinity", "\u{221e}"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            header_row: 0,
+            skip_lines: 0,
+            encoding: None,
+            timestamp: TimestampOptions::default(),
+            layout: TableLayout::Wide,
+            columns: Vec::new(),
+            quality: QualityOptions::default(),
+            sheet: None,
+            cell_range: None,
+            conflict: ConflictPolicy::Last,
+            alignment: Alignment::default(),
+            naming: ColumnNaming::Merge,
+            file_options: HashMap::new(),
+        }
+    }
+}
+
+/// Longest unit accepted after a number, in characters.
+const MAX_UNIT_CHARS: usize = 12;
+
+/// Whether `unit`, the text after a number, reads as a unit such as "°C", "%", "kW"
+/// or "m³/h" rather than free text.
+fn is_unit(unit: &str) -> bool {
+    unit.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || "°%‰/".contains(c))
+        && unit.chars().count() <= MAX_UNIT_CHARS
+        && !unit.contains(char::is_whitespace)
+}
+
+impl ImportOptions {
+    /// Parses a numeric cell, honouring the configured separators and NaN/Inf tokens
+    /// and ignoring a unit written after the number.
+    pub fn parse_number(&self, field: &str) -> Option<f64> {
+        self.parse_number_unit(field).map(|(value, _)| value)
+    }
+
+    /// Parses a numeric cell into its value and the unit written after it, if any,
+    /// e.g. "12,5 °C" into 12.5 and "°C" with `,` as the decimal separator.
+    pub fn parse_number_unit<'a>(&self, field: &'a str) -> Option<(f64, Option<&'a str>)> {
+        let field = field.trim();
+        if field.is_empty() {
+            return None;
+        }
+        // Configured tokens come first; Rust's own "nan" and "inf" spellings are not numbers
+        if let Some(value) = self.special_number(field) {
+            return Some((value, None));
+        }
+        if self.decimal_separator == '.' && self.thousands_separator.is_none() {
+            if let Ok(value) = field.parse::<f64>() {
+                return value.is_finite().then_some((value, None));
+            }
+        }
+
+        let end = self.number_end(field);
+        let unit = field[end..].trim_start();
+        if !unit.is_empty() && !is_unit(unit) {
+            return None;
+        }
+        let mut number = String::with_capacity(end);
+        for c in field[..end].chars() {
+            if c == self.decimal_separator {
+                number.push('.');
+            } else if !self.is_thousands_separator(c) {
+                number.push(c);
+            }
+        }
+        let value = number.parse::<f64>().ok().filter(|v| v.is_finite())?;
+        Some((value, (!unit.is_empty()).then_some(unit)))
+    }
+
+    fn special_number(&self, field: &str) -> Option<f64> {
+        let is_token =
+            |tokens: &[String], value: &str| tokens.iter().any(|t| t.eq_ignore_ascii_case(value));
+        if is_token(&self.nan_values, field) {
+            return Some(f64::NAN);
+        }
+        let (sign, magnitude) = match field.strip_prefix('-') {
+            Some(rest) => (-1.0, rest),
+            None => (1.0, field.strip_prefix('+').unwrap_or(field)),
+        };
+        is_token(&self.inf_values, magnitude.trim_start()).then_some(sign * f64::INFINITY)
+    }
+
+    fn is_thousands_separator(&self, c: char) -> bool {
+        match self.thousands_separator {
+            // Spreadsheets group digits with no-break spaces
+            Some(' ') => c == ' ' || c == '\u{a0}' || c == '\u{202f}',
+            Some(separator) => c == separator,
+            None => false,
+        }
+    }
+
+    /// Byte length of the number at the start of `field`: digits, separators, a
+    /// leading sign and an exponent.
+    fn number_end(&self, field: &str) -> usize {
+        let mut end = 0;
+        let mut prev = None;
+        let mut chars = field.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, n)| n);
+            let is_exponent = matches!(c, 'e' | 'E')
+                && prev.is_some_and(|p: char| p.is_ascii_digit())
+                && next.is_some_and(|n| n.is_ascii_digit() || n == '-' || n == '+');
+            let is_sign = matches!(c, '-' | '+') && (i == 0 || matches!(prev, Some('e' | 'E')));
+            if !(c.is_ascii_digit()
+                || c == self.decimal_separator
+                || self.is_thousands_separator(c)
+                || is_exponent
+                || is_sign)
+            {
+                break;
+            }
+            end = i + c.len_utf8();
+            prev = Some(c);
+        }
+        end
+    }
+}
+
+/// Opens `path` as a CSV reader positioned on the header row, transcoding to
+/// UTF-8 and discarding the preamble as configured in `options`.
+pub(crate) fn open_csv_reader(path: &str, options: &ImportOptions) -> Result<CsvReader, String> {
+    csv_reader_over(archive::open_input(path)?, path, options)
+}
+
+/// Input that counts the bytes read from it.
+struct CountingReader {
+    inner: Box<dyn Read + Send>,
+    count: Arc<AtomicU64>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// `open_csv_reader` over an input already opened from `path`.
+fn csv_reader_over(
+    input: Box<dyn Read + Send>,
+    path: &str,
+    options: &ImportOptions,
+) -> Result<CsvReader, String> {
+    let mut reader = decode_input(input, options)?;
+
+    let mut line = Vec::new();
+    for _ in 0..options.header_row {
+        line.clear();
+        if reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            return Err(format!(
+                "{}: header row {} is past the end of the file",
+                path, options.header_row
+            ));
+        }
+    }
+
+    let mut rdr = csv_builder(options)?.from_reader(reader);
+    // Populate the header before skipping so skipped lines are never mistaken for it
+    rdr.byte_headers().map_err(|e| e.to_string())?;
+
+    let mut skipped = csv::ByteRecord::new();
+    for _ in 0..options.skip_lines {
+        if !rdr
+            .read_byte_record(&mut skipped)
+            .map_err(|e| e.to_string())?
+        {
+            break;
+        }
+    }
+
+    Ok(rdr)
+}
+
+/// The encoding named by `options.encoding`, or `None` to sniff the BOM.
+pub(crate) fn configured_encoding(
+    options: &ImportOptions,
+) -> Result<Option<&'static Encoding>, String> {
+    match &options.encoding {
+        Some(label) if !label.trim().is_empty() => Encoding::for_label(label.trim().as_bytes())
+            .map(Some)
+            .ok_or_else(|| format!("Unknown encoding: {}", label)),
+        _ => Ok(None),
+    }
+}
+
+/// Transcodes raw input to buffered UTF-8.
+pub(crate) fn decode_input(
+    input: Box<dyn Read + Send>,
+    options: &ImportOptions,
+) -> Result<Box<dyn BufRead + Send>, String> {
+    let decoded = DecodeReaderBytesBuilder::new()
+        .encoding(configured_encoding(options)?)
+        .build(input);
     // Buffered reader for performance
-    let mut rdr = csv::Reader::from_reader(BufReader::new(file));
+    Ok(Box::new(BufReader::new(decoded)))
+}
+
+fn csv_builder(options: &ImportOptions) -> Result<csv::ReaderBuilder, String> {
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .delimiter(ascii_byte(options.delimiter, "delimiter")?)
+        .quote(ascii_byte(options.quote, "quote")?)
+        .flexible(true);
+    Ok(builder)
+}
+
+fn ascii_byte(c: char, what: &str) -> Result<u8, String> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(format!(
+            "The {} must be a single ASCII character, got {:?}",
+            what, c
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// `yyyy-mm-dd[THH:MM:SS[.fff]][Z|+hh:mm]`
+    Iso8601,
+    EpochSeconds,
+    EpochMillis,
+    /// `dd/mm/yyyy [HH:MM[:SS[.fff]]]`
+    DayFirst,
+    /// `mm/dd/yyyy [HH:MM[:SS[.fff]]]`
+    MonthFirst,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DateOrder {
+    Ymd,
+    Dmy,
+    Mdy,
+}
+
+/// Number of non-empty timestamp values inspected when detecting the format.
+const TIMESTAMP_SAMPLE_SIZE: usize = 200;
+
+/// Epoch values at or above this magnitude are treated as milliseconds
+/// (1e11 seconds would be beyond the year 5000).
+const EPOCH_MILLIS_THRESHOLD: f64 = 1e11;
+
+/// Parses timestamp strings of one column into UTC instants.
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+    format: Option<TimestampFormat>,
+    timezone: Tz,
+}
+
+impl TimestampParser {
+    pub fn new(options: &TimestampOptions) -> Result<Self, String> {
+        let timezone = match &options.timezone {
+            Some(name) if !name.trim().is_empty() => name
+                .trim()
+                .parse::<Tz>()
+                .map_err(|_| format!("Unknown timezone: {}", name))?,
+            _ => Tz::UTC,
+        };
+        Ok(TimestampParser {
+            format: None,
+            timezone,
+        })
+    }
+
+    /// Picks the first format that parses every sample. Leaves the parser in
+    /// per-value auto-detection mode when no single format fits.
+    pub fn detect<'a>(
+        mut self,
+        samples: impl IntoIterator<Item = &'a str>,
+        day_first: Option<bool>,
+    ) -> Self {
+        let samples: Vec<&str> = samples
+            .into_iter()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .take(TIMESTAMP_SAMPLE_SIZE)
+            .collect();
+        if samples.is_empty() {
+            return self;
+        }
+
+        let candidates: &[TimestampFormat] = match day_first {
+            Some(false) => &[
+                TimestampFormat::Iso8601,
+                TimestampFormat::EpochMillis,
+                TimestampFormat::EpochSeconds,
+                TimestampFormat::MonthFirst,
+                TimestampFormat::DayFirst,
+            ],
+            _ => &[
+                TimestampFormat::Iso8601,
+                TimestampFormat::EpochMillis,
+                TimestampFormat::EpochSeconds,
+                TimestampFormat::DayFirst,
+                TimestampFormat::MonthFirst,
+            ],
+        };
+
+        self.format = candidates
+            .iter()
+            .copied()
+            .find(|&format| samples.iter().all(|s| self.parse_with(s, format).is_some()));
+        self
+    }
+
+    pub fn format(&self) -> Option<TimestampFormat> {
+        self.format
+    }
+
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        match self.format {
+            Some(format) => self.parse_with(value, format),
+            None => [
+                TimestampFormat::Iso8601,
+                TimestampFormat::EpochMillis,
+                TimestampFormat::EpochSeconds,
+                TimestampFormat::DayFirst,
+                TimestampFormat::MonthFirst,
+            ]
+            .into_iter()
+            .find_map(|format| self.parse_with(value, format)),
+        }
+    }
+
+    fn parse_with(&self, value: &str, format: TimestampFormat) -> Option<DateTime<Utc>> {
+        match format {
+            TimestampFormat::Iso8601 => {
+                if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+                    return Some(dt.with_timezone(&Utc));
+                }
+                let naive = parse_wall_clock(value, DateOrder::Ymd)?;
+                self.localize(naive)
+            }
+            TimestampFormat::EpochSeconds => {
+                let secs = value.parse::<f64>().ok()?;
+                if !secs.is_finite() || secs.abs() >= EPOCH_MILLIS_THRESHOLD {
+                    return None;
+                }
+                DateTime::from_timestamp_millis((secs * 1000.0).round() as i64)
+            }
+            TimestampFormat::EpochMillis => {
+                let millis = value.parse::<f64>().ok()?;
+                if !millis.is_finite() || millis.abs() < EPOCH_MILLIS_THRESHOLD {
+                    return None;
+                }
+                DateTime::from_timestamp_millis(millis.round() as i64)
+            }
+            TimestampFormat::DayFirst => self.localize(parse_wall_clock(value, DateOrder::Dmy)?),
+            TimestampFormat::MonthFirst => self.localize(parse_wall_clock(value, DateOrder::Mdy)?),
+        }
+    }
+
+    /// Interprets a wall-clock time in the configured source timezone.
+    pub fn localize(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        // Ambiguous wall-clock times (DST fall-back) resolve to the earlier instant.
+        self.timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+/// Parses a date in the given component order with an optional
+/// `HH:MM[:SS[.fff]]` time part and `AM`/`PM` suffix.
+fn parse_wall_clock(value: &str, order: DateOrder) -> Option<NaiveDateTime> {
+    let (date_part, time_part) = match value.find([' ', 'T']) {
+        Some(i) => (&value[..i], value[i + 1..].trim()),
+        None => (value, ""),
+    };
+
+    let mut parts = date_part.split(['/', '-', '.']);
+    let a = parts.next()?;
+    let b = parts.next()?;
+    let c = parts.next()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let (year, month, day) = match order {
+        DateOrder::Ymd => (a, b, c),
+        DateOrder::Dmy => (c, b, a),
+        DateOrder::Mdy => (c, a, b),
+    };
+    if year.len() != 4 {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+
+    let time = if time_part.is_empty() {
+        NaiveTime::MIN
+    } else {
+        parse_clock(time_part)?
+    };
+    Some(date.and_time(time))
+}
+
+fn parse_clock(value: &str) -> Option<NaiveTime> {
+    let upper = value.to_ascii_uppercase();
+    let (clock, pm) = if let Some(rest) = upper.strip_suffix("PM") {
+        (rest.trim_end(), Some(true))
+    } else if let Some(rest) = upper.strip_suffix("AM") {
+        (rest.trim_end(), Some(false))
+    } else {
+        (upper.as_str(), None)
+    };
+
+    let mut parts = clock.split(':');
+    let mut hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+    let (second, nanos) = match parts.next() {
+        Some(sec) => {
+            let (whole, frac) = match sec.find(['.', ',']) {
+                Some(i) => (&sec[..i], &sec[i + 1..]),
+                None => (sec, ""),
+            };
+            let nanos = if frac.is_empty() {
+                0
+            } else {
+                if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32)
+            };
+            (whole.parse().ok()?, nanos)
+        }
+        None => (0, 0),
+    };
+    if parts.next().is_some() {
+        return None;
+    }
 
+    match pm {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some(true) if hour != 12 => hour += 12,
+        Some(false) if hour == 12 => hour = 0,
+        _ => {}
+    }
+    NaiveTime::from_hms_nano_opt(hour, minute, second, nanos)
+}
+
+use rayon::prelude::*;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Raw bytes read and parsed per block. Large enough to keep every rayon worker busy,
+/// small enough that the raw records stay a fraction of the output however many
+/// columns a row has.
+const READ_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// Refills `chunk` with records up to about `READ_CHUNK_BYTES`, reusing the buffers of
+/// the previous block. Leaves `chunk` empty at end of input.
+fn fill_chunk<R: std::io::Read>(
+    rdr: &mut csv::Reader<R>,
+    chunk: &mut Vec<csv::ByteRecord>,
+) -> Result<(), String> {
+    let mut len = 0;
+    let mut bytes = 0;
+    while bytes < READ_CHUNK_BYTES {
+        if len == chunk.len() {
+            chunk.push(csv::ByteRecord::new());
+        }
+        if !rdr
+            .read_byte_record(&mut chunk[len])
+            .map_err(|e| e.to_string())?
+        {
+            break;
+        }
+        // Count a separator per field, so rows of empty cells still fill the block
+        bytes += chunk[len].as_slice().len() + chunk[len].len();
+        len += 1;
+    }
+    chunk.truncate(len);
+    Ok(())
+}
+
+/// A run of rows of a wide file parsed straight into column buffers. Boolean and state
+/// cells are coded with the piece's copy of each state table and recoded as the piece
+/// joins the columns.
+struct ParsedPiece {
+    timestamps: Vec<DateTime<Utc>>,
+    columns: Vec<SensorColumn>,
+    report: FileReport,
+}
+
+/// Maximum number of offending values kept per column in the ingestion report.
+const MAX_BAD_SAMPLES: usize = 5;
+/// Maximum number of ragged rows listed individually per file.
+const MAX_RAGGED_ROWS: usize = 20;
+
+/// A cell that could not be parsed, with the 1-based line it came from
+/// (the row number for Parquet input).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BadCell {
+    pub line: u64,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ColumnReport {
+    pub name: String,
+    pub empty_cells: usize,
+    pub unparsable_cells: usize,
+    /// Values flagged bad by their quality column, or given as a bad-value token.
+    pub bad_quality_cells: usize,
+    pub samples: Vec<BadCell>,
+    /// First unit found after the column's numbers, e.g. "°C".
+    pub unit: Option<String>,
+}
+
+/// A row whose field count differs from the header.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RaggedRow {
+    pub line: u64,
+    pub fields: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileReport {
+    pub path: String,
+    pub total_rows: usize,
+    pub expected_fields: usize,
+    /// Number of rows with the wrong field count; `ragged_rows` lists the first few.
+    pub ragged_row_count: usize,
+    pub ragged_rows: Vec<RaggedRow>,
+    /// Rows dropped because their timestamp was empty or unparsable, since they
+    /// cannot be placed on the time axis.
+    pub missing_timestamp_rows: usize,
+    /// Long-format rows whose quality was not good; dropped unless
+    /// `keep_bad_quality` is set.
+    pub bad_quality_rows: usize,
+    /// One entry per header, the timestamp column included.
+    pub columns: Vec<ColumnReport>,
+    /// Shift applied to the file's timestamps from its `FileOptions`.
+    pub clock: Option<ClockCorrection>,
+    /// Bytes read from a CSV file, including any rows written while it was loading;
+    /// following the file resumes here.
+    #[serde(skip)]
+    pub bytes_read: Option<u64>,
+}
+
+/// Cells of one column where overlapping files held different values.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnConflict {
+    pub column: String,
+    /// Timestamps of the first and last conflicting cell.
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub conflicting_cells: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct IngestReport {
+    pub files: Vec<FileReport>,
+    pub conflicts: Vec<ColumnConflict>,
+}
+
+impl FileReport {
+    pub(crate) fn record_bad_cell(&mut self, column: usize, line: u64, value: &[u8]) {
+        let report = &mut self.columns[column];
+        report.unparsable_cells += 1;
+        if report.samples.len() < MAX_BAD_SAMPLES {
+            report.samples.push(BadCell {
+                line,
+                value: String::from_utf8_lossy(value).into_owned(),
+            });
+        }
+    }
+
+    pub(crate) fn record_unit(&mut self, column: usize, unit: &str) {
+        let report = &mut self.columns[column];
+        if report.unit.is_none() {
+            report.unit = Some(unit.to_string());
+        }
+    }
+
+    fn record_ragged_row(&mut self, line: u64, fields: usize) {
+        self.ragged_row_count += 1;
+        if self.ragged_rows.len() < MAX_RAGGED_ROWS {
+            self.ragged_rows.push(RaggedRow { line, fields });
+        }
+    }
+
+    /// Adds the counts of a later part of the same file, keeping the earliest samples.
+    fn absorb(&mut self, other: FileReport) {
+        self.total_rows += other.total_rows;
+        for (mine, theirs) in self.columns.iter_mut().zip(other.columns) {
+            mine.empty_cells += theirs.empty_cells;
+            mine.unparsable_cells += theirs.unparsable_cells;
+            mine.bad_quality_cells += theirs.bad_quality_cells;
+            let room = MAX_BAD_SAMPLES.saturating_sub(mine.samples.len());
+            mine.samples.extend(theirs.samples.into_iter().take(room));
+            if mine.unit.is_none() {
+                mine.unit = theirs.unit;
+            }
+        }
+        self.ragged_row_count += other.ragged_row_count;
+        let room = MAX_RAGGED_ROWS.saturating_sub(self.ragged_rows.len());
+        self.ragged_rows
+            .extend(other.ragged_rows.into_iter().take(room));
+        self.missing_timestamp_rows += other.missing_timestamp_rows;
+        self.bad_quality_rows += other.bad_quality_rows;
+    }
+}
+
+/// Parsed rows and diagnostics of one rayon job; jobs are combined in input order.
+struct BlockTally<R> {
+    rows: Vec<R>,
+    report: FileReport,
+}
+
+impl<R> BlockTally<R> {
+    fn new(field_count: usize) -> Self {
+        BlockTally {
+            rows: Vec::new(),
+            report: FileReport {
+                columns: vec![ColumnReport::default(); field_count],
+                ..Default::default()
+            },
+        }
+    }
+
+    fn merge(mut self, other: BlockTally<R>) -> Self {
+        self.rows.extend(other.rows);
+        self.report.absorb(other.report);
+        self
+    }
+}
+
+/// Headers recognised as a timestamp column, compared case-insensitively.
+const TIMESTAMP_NAMES: [&str; 11] = [
+    "timestamp",
+    "time",
+    "datetime",
+    "date/time",
+    "date time",
+    "date_time",
+    "time stamp",
+    "ts",
+    "zeitstempel",
+    "datum/zeit",
+    "datum zeit",
+];
+/// Headers of the date half of a split date/time pair.
+const DATE_NAMES: [&str; 2] = ["date", "datum"];
+/// Headers of the time half of a split date/time pair.
+const TIME_NAMES: [&str; 3] = ["time", "zeit", "uhrzeit"];
+
+/// Where the timestamps of a file come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampColumn {
+    Single(usize),
+    /// Separate date and time columns, joined with a space before parsing.
+    Split {
+        date: usize,
+        time: usize,
+    },
+}
+
+impl TimestampColumn {
+    pub fn covers(&self, column: usize) -> bool {
+        match *self {
+            TimestampColumn::Single(i) => i == column,
+            TimestampColumn::Split { date, time } => date == column || time == column,
+        }
+    }
+
+    /// Column that empty and unparsable timestamps are reported against.
+    pub fn report_column(&self) -> usize {
+        match *self {
+            TimestampColumn::Single(i) => i,
+            TimestampColumn::Split { date, .. } => date,
+        }
+    }
+
+    pub fn header(&self, header_list: &[String]) -> String {
+        match *self {
+            TimestampColumn::Single(i) => header_list[i].clone(),
+            TimestampColumn::Split { date, time } => {
+                format!("{} {}", header_list[date], header_list[time])
+            }
+        }
+    }
+
+    /// The timestamp text of a record; empty when the date is missing.
+    pub fn text<'a>(&self, record: &'a csv::ByteRecord) -> Cow<'a, str> {
+        let field = |i: usize| String::from_utf8_lossy(record.get(i).unwrap_or_default());
+        match *self {
+            TimestampColumn::Single(i) => field(i),
+            TimestampColumn::Split { date, time } => {
+                let date = field(date);
+                if date.trim().is_empty() {
+                    return Cow::Borrowed("");
+                }
+                Cow::Owned(format!("{} {}", date.trim(), field(time).trim()))
+            }
+        }
+    }
+}
+
+/// Timestamp column and format of a wide CSV file, detected from its first block.
+#[derive(Debug, Clone)]
+pub struct DetectedTimestamp {
+    pub column: Option<TimestampColumn>,
+    pub parser: TimestampParser,
+}
+
+/// Locates the timestamp column of a wide file and detects its format from `block`.
+fn detect_timestamp(
+    path: &str,
+    header_list: &[String],
+    block: &[csv::ByteRecord],
+    options: &ImportOptions,
+) -> Result<DetectedTimestamp, String> {
+    let column = locate_timestamp_column(header_list, block, options)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let parser = detect_timestamp_parser(block, column, options)?;
+    Ok(DetectedTimestamp { column, parser })
+}
+
+/// Detects the timestamp format from the leading rows so every row is parsed the same way.
+fn detect_timestamp_parser(
+    block: &[csv::ByteRecord],
+    ts_column: Option<TimestampColumn>,
+    options: &ImportOptions,
+) -> Result<TimestampParser, String> {
+    let samples: Vec<Cow<str>> = ts_column
+        .into_iter()
+        .flat_map(|column| block.iter().map(move |r| column.text(r)))
+        .filter(|text| !text.trim().is_empty())
+        .take(TIMESTAMP_SAMPLE_SIZE)
+        .collect();
+    let ts_parser = TimestampParser::new(&options.timestamp)?.detect(
+        samples.iter().map(|text| text.as_ref()),
+        options.timestamp.day_first,
+    );
+    println!("Detected timestamp format: {:?}", ts_parser.format());
+    Ok(ts_parser)
+}
+
+fn is_named(header: &str, names: &[&str]) -> bool {
+    names.iter().any(|name| header.eq_ignore_ascii_case(name))
+}
+
+fn find_header(header_list: &[String], names: &[&str]) -> Option<usize> {
+    header_list.iter().position(|h| is_named(h, names))
+}
+
+/// The timestamp column named in `options`, or the first header with a timestamp-like
+/// name. Used by the Parquet and Excel readers, which do not join date/time pairs.
+pub(crate) fn find_timestamp_column(
+    header_list: &[String],
+    options: &TimestampOptions,
+) -> Result<Option<usize>, String> {
+    match &options.column {
+        Some(name) => find_column(header_list, Some(name), &[])
+            .map(Some)
+            .ok_or_else(|| format!("Timestamp column {} not found", name)),
+        None if options.date_column.is_some() || options.time_column.is_some() => {
+            Err("Separate date and time columns are only supported for CSV files".to_string())
+        }
+        None => Ok(find_header(header_list, &TIMESTAMP_NAMES)),
+    }
+}
+
+/// Locates the timestamp column of a CSV file. In order: the column or date/time pair
+/// configured in `options`, a timestamp-like header whose values parse, a date/time
+/// header pair whose joined values parse, and finally any column whose values parse as
+/// calendar dates. Numbers only count as epoch timestamps under a timestamp-like header.
+pub(crate) fn locate_timestamp_column(
+    header_list: &[String],
+    block: &[csv::ByteRecord],
+    options: &ImportOptions,
+) -> Result<Option<TimestampColumn>, String> {
+    let timestamp = &options.timestamp;
+    if timestamp.column.is_some() {
+        return Ok(find_timestamp_column(header_list, timestamp)?.map(TimestampColumn::Single));
+    }
+    match (&timestamp.date_column, &timestamp.time_column) {
+        (Some(date), Some(time)) => {
+            let find = |name: &String| {
+                find_column(header_list, Some(name), &[])
+                    .ok_or_else(|| format!("Timestamp column {} not found", name))
+            };
+            return Ok(Some(TimestampColumn::Split {
+                date: find(date)?,
+                time: find(time)?,
+            }));
+        }
+        (None, None) => {}
+        _ => return Err("dateColumn and timeColumn must be set together".to_string()),
+    }
+
+    let parser = TimestampParser::new(timestamp)?;
+    let parses = |column: TimestampColumn, allow_epoch: bool| {
+        let samples: Vec<Cow<str>> = block
+            .iter()
+            .map(|r| column.text(r))
+            .filter(|text| !text.trim().is_empty())
+            .take(TIMESTAMP_SAMPLE_SIZE)
+            .collect();
+        let detected = parser.clone().detect(
+            samples.iter().map(|text| text.as_ref()),
+            timestamp.day_first,
+        );
+        match detected.format() {
+            Some(TimestampFormat::EpochSeconds | TimestampFormat::EpochMillis) => allow_epoch,
+            Some(_) => true,
+            None => false,
+        }
+    };
+
+    let named: Vec<usize> = (0..header_list.len())
+        .filter(|&i| is_named(&header_list[i], &TIMESTAMP_NAMES))
+        .collect();
+    if let Some(&i) = named
+        .iter()
+        .find(|&&i| parses(TimestampColumn::Single(i), true))
+    {
+        return Ok(Some(TimestampColumn::Single(i)));
+    }
+    if let (Some(date), Some(time)) = (
+        find_header(header_list, &DATE_NAMES),
+        find_header(header_list, &TIME_NAMES),
+    ) {
+        let split = TimestampColumn::Split { date, time };
+        if parses(split, false) {
+            return Ok(Some(split));
+        }
+    }
+    if let Some(i) = (0..header_list.len()).find(|&i| parses(TimestampColumn::Single(i), false)) {
+        return Ok(Some(TimestampColumn::Single(i)));
+    }
+    // Nothing parses: keep the named column so its rows are reported as bad timestamps
+    Ok(named.first().map(|&i| TimestampColumn::Single(i)))
+}
+
+/// Index of the header matching `name`, or the first of `fallbacks` when no name is given.
+fn find_column(header_list: &[String], name: Option<&str>, fallbacks: &[&str]) -> Option<usize> {
+    match name {
+        Some(name) => header_list
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name.trim())),
+        None => header_list
+            .iter()
+            .position(|h| fallbacks.iter().any(|f| h.eq_ignore_ascii_case(f))),
+    }
+}
+
+/// File line number of a record. csv counts lines from the header, so the preamble
+/// before it is added back.
+fn record_line(record: &csv::ByteRecord, options: &ImportOptions) -> u64 {
+    record.position().map_or(0, |p| p.line()) + options.header_row as u64
+}
+
+pub(crate) fn new_file_report(path: &str, header_list: &[String]) -> FileReport {
+    FileReport {
+        path: path.to_string(),
+        expected_fields: header_list.len(),
+        columns: header_list
+            .iter()
+            .map(|h| ColumnReport {
+                name: h.clone(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+type CsvReader = csv::Reader<Box<dyn BufRead + Send>>;
+
+/// Reads one input file, picking the reader from its extension.
+pub fn read_file(
+    path: &str,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("parquet") => parquet_io::read_parquet(path, options),
+        Some("xlsx") | Some("xlsm") => xlsx_reader::read_xlsx(path, options),
+        _ => read_csv(path, options),
+    }
+}
+
+/// Reads a CSV file to its end, recording in the report how many bytes were read.
+pub fn read_csv(
+    path: &str,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let input = CountingReader {
+        inner: archive::open_input(path)?,
+        count: bytes_read.clone(),
+    };
+    let rdr = csv_reader_over(Box::new(input), path, options)?;
+    let (data, mut report) = read_csv_records(rdr, path, options)?;
+    report.bytes_read = Some(bytes_read.load(Ordering::Relaxed));
+    Ok((data, report))
+}
+
+fn read_csv_records(
+    mut rdr: CsvReader,
+    path: &str,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
     // Get headers
     let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
     let header_list: Vec<String> = headers.iter().map(|s| s.trim().to_string()).collect();
 
-    // Identify timestamp column index
-    let timestamp_idx = header_list
-        .iter()
-        .position(|h| h.eq_ignore_ascii_case("timestamp") || h.eq_ignore_ascii_case("time"));
-
-    // 1. Read all raw byte records into memory (Sequential I/O)
-    let io_start = Instant::now();
-    let mut raw_records = Vec::new();
-    let mut byte_record = csv::ByteRecord::new();
-    while rdr
-        .read_byte_record(&mut byte_record)
-        .map_err(|e| e.to_string())?
-    {
-        raw_records.push(byte_record.clone());
+    if let TableLayout::Long(layout) = &options.layout {
+        let tag_idx = find_column(
+            &header_list,
+            layout.tag_column.as_deref(),
+            &["tag", "tagname", "name", "sensor"],
+        );
+        let value_idx = find_column(
+            &header_list,
+            layout.value_column.as_deref(),
+            &["value", "val"],
+        );
+        if let (Some(tag_idx), Some(value_idx)) = (tag_idx, value_idx) {
+            let quality = match &layout.quality_column {
+                Some(name) => Some(
+                    find_column(&header_list, Some(name), &[])
+                        .ok_or_else(|| format!("{}: quality column {} not found", path, name))?,
+                ),
+                None => None,
+            };
+            let columns = LongColumns {
+                tag: tag_idx,
+                value: value_idx,
+                quality,
+            };
+
+            return read_long_records(rdr, path, header_list, columns, layout, options);
+        }
     }
-    println!("Reading raw bytes took: {:?}", io_start.elapsed());
 
-    // 2. Parse records in parallel (Parallel CPU)
-    let parse_start = Instant::now();
-    let records: Vec<CsvRecord> = raw_records
-        .par_iter()
-        .map(|raw_record| {
-            let mut timestamp: Option<String> = None;
-            let mut values: Vec<Option<f64>> = Vec::with_capacity(header_list.len());
+    read_wide_records(rdr, path, header_list, None, options)
+}
+
+/// Header and column layout of a wide CSV file, for parsing rows appended to it later.
+/// The layout comes from the same first block the initial read used, so appended rows
+/// are typed and paired exactly as the loaded ones.
+pub fn probe_csv(path: &str, options: &ImportOptions) -> Result<(Vec<String>, WideLayout), String> {
+    let mut rdr = open_csv_reader(path, options)?;
+    let headers = rdr.headers().map_err(|e| e.to_string())?;
+    let header_list: Vec<String> = headers.iter().map(|s| s.trim().to_string()).collect();
+    let mut block = Vec::new();
+    fill_chunk(&mut rdr, &mut block)?;
+    let layout = WideLayout::detect(path, &header_list, &block, options)?;
+    Ok((header_list, layout))
+}
 
-            for (i, field) in raw_record.iter().enumerate() {
-                let field_str = std::str::from_utf8(field).unwrap_or("");
+/// Parses complete lines appended to a wide CSV file, using the header and layout
+/// from `probe_csv`. Line numbers in the report count from the start of `bytes`.
+pub fn read_appended_rows(
+    path: &str,
+    bytes: Vec<u8>,
+    header_list: &[String],
+    layout: &WideLayout,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let reader = decode_input(Box::new(Cursor::new(bytes)), options)?;
+    let rdr = csv_builder(options)?.has_headers(false).from_reader(reader);
+    let options = ImportOptions {
+        header_row: 0,
+        ..options.clone()
+    };
+    read_wide_records(
+        rdr,
+        path,
+        header_list.to_vec(),
+        Some(layout.clone()),
+        &options,
+    )
+}
 
-                if Some(i) == timestamp_idx {
-                    if !field_str.trim().is_empty() {
-                        timestamp = Some(field_str.to_string());
-                    }
-                    // Placeholder for timestamp in values array to keep indices aligned with headers
-                    values.push(None);
+/// Where each field of a wide file goes and how it is read: the timestamp column and
+/// format, quality pairs, and the type of every selected column.
+#[derive(Debug, Clone)]
+pub struct WideLayout {
+    timestamp: DetectedTimestamp,
+    /// Quality column of each column, by header position.
+    quality_of: Vec<Option<usize>>,
+    /// Column buffer each field goes to; `None` for the timestamp, quality and
+    /// unselected columns.
+    column_slots: Vec<Option<usize>>,
+    /// Empty column of each slot, carrying its name and type.
+    columns: Vec<SensorColumn>,
+}
+
+impl WideLayout {
+    /// Detects the layout from the first block of a file.
+    fn detect(
+        path: &str,
+        header_list: &[String],
+        block: &[csv::ByteRecord],
+        options: &ImportOptions,
+    ) -> Result<Self, String> {
+        let timestamp = detect_timestamp(path, header_list, block, options)?;
+        let filter = ColumnFilter::new(&options.columns)?;
+        let quality = &options.quality;
+        let quality_columns = quality
+            .pair_columns(header_list, |i| {
+                block
+                    .iter()
+                    .filter_map(move |r| r.get(i))
+                    .map(|f| String::from_utf8_lossy(f).into_owned())
+            })
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let mut columns: Vec<SensorColumn> = Vec::new();
+        let column_slots = header_list
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                if timestamp.column.is_some_and(|c| c.covers(i))
+                    || quality_columns.is_quality[i]
+                    || !filter.matches(h)
+                {
+                    return None;
+                }
+                let samples = block
+                    .iter()
+                    .filter_map(|r| r.get(i))
+                    .filter_map(|f| std::str::from_utf8(f).ok())
+                    .filter(|f| !quality.is_bad_value(f));
+                let column_type = infer_column_type(samples, options);
+                columns.push(SensorColumn::new(h.clone()).with_type(column_type));
+                Some(columns.len() - 1)
+            })
+            .collect();
+        Ok(WideLayout {
+            timestamp,
+            quality_of: quality_columns.of,
+            column_slots,
+            columns,
+        })
+    }
+}
+
+/// Reads wide records. The layout is detected from the first block unless given.
+fn read_wide_records(
+    mut rdr: CsvReader,
+    path: &str,
+    header_list: Vec<String>,
+    layout: Option<WideLayout>,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let total_start = Instant::now();
+
+    // 1. Read the first block; it also serves as the sample for layout detection
+    let mut current = Vec::new();
+    fill_chunk(&mut rdr, &mut current)?;
+    let WideLayout {
+        timestamp:
+            DetectedTimestamp {
+                column: ts_column,
+                parser: ts_parser,
+            },
+        quality_of,
+        column_slots,
+        columns,
+    } = match layout {
+        Some(layout) => layout,
+        None => WideLayout::detect(path, &header_list, &current, options)?,
+    };
+    let timestamp_header = ts_column
+        .map(|c| c.header(&header_list))
+        .unwrap_or_else(|| "timestamp".to_string());
+    let quality = &options.quality;
+    let mut columns: Vec<SensorColumn> = columns.iter().map(|c| c.empty_like(0)).collect();
+    let mut timestamps: Vec<DateTime<Utc>> = Vec::new();
+
+    let mut report = new_file_report(path, &header_list);
+
+    // Parses a run of records into pieces of `columns`, which supply the types and
+    // state tables so far
+    let parse_records = |records: &[csv::ByteRecord], columns: &[SensorColumn]| {
+        let mut piece = ParsedPiece {
+            timestamps: Vec::with_capacity(records.len()),
+            columns: columns
+                .iter()
+                .map(|c| c.empty_like(records.len()))
+                .collect(),
+            report: FileReport {
+                columns: vec![ColumnReport::default(); header_list.len()],
+                ..Default::default()
+            },
+        };
+        let report = &mut piece.report;
+        for raw_record in records {
+            let line = record_line(raw_record, options);
+            report.total_rows += 1;
+            if raw_record.len() != header_list.len() {
+                report.record_ragged_row(line, raw_record.len());
+            }
+
+            let mut timestamp: Option<DateTime<Utc>> = None;
+            if let Some(ts_column) = ts_column {
+                let text = ts_column.text(raw_record);
+                if text.trim().is_empty() {
+                    report.columns[ts_column.report_column()].empty_cells += 1;
                 } else {
-                    let val = if field_str.trim().is_empty() {
-                        None
-                    } else {
-                        field_str.parse::<f64>().ok()
+                    timestamp = ts_parser.parse(&text);
+                    if timestamp.is_none() {
+                        report.record_bad_cell(ts_column.report_column(), line, text.as_bytes());
+                    }
+                }
+            }
+            // Cells of rows without a timestamp are still checked for the report
+            match timestamp {
+                Some(timestamp) => piece.timestamps.push(timestamp),
+                None => report.missing_timestamp_rows += 1,
+            }
+
+            for (i, slot) in column_slots.iter().enumerate() {
+                let Some(col) = *slot else {
+                    continue;
+                };
+                let column = &mut piece.columns[col];
+                let (value, bad) = 'cell: {
+                    // Short rows have no field for the trailing columns
+                    let Some(field) = raw_record.get(i) else {
+                        break 'cell (None, false);
                     };
-                    values.push(val);
+                    let Ok(field_str) = std::str::from_utf8(field) else {
+                        report.record_bad_cell(i, line, field);
+                        break 'cell (None, false);
+                    };
+                    let is_empty = field_str.trim().is_empty();
+                    if is_empty {
+                        report.columns[i].empty_cells += 1;
+                    }
+                    if quality.is_bad_value(field_str) {
+                        report.columns[i].bad_quality_cells += 1;
+                        break 'cell (None, true);
+                    }
+                    let good = quality_of[i]
+                        .and_then(|q| raw_record.get(q))
+                        .is_none_or(|q| quality.is_good(&String::from_utf8_lossy(q)));
+                    let bad = !good && !is_empty;
+                    if bad {
+                        report.columns[i].bad_quality_cells += 1;
+                    }
+                    let value = match column.column_type {
+                        ColumnType::Numeric => {
+                            let parsed = options.parse_number_unit(field_str);
+                            if let Some((_, Some(unit))) = parsed {
+                                report.record_unit(i, unit);
+                            }
+                            parsed.map(|(value, _)| value)
+                        }
+                        // Booleans keep their spelling as the label of their value
+                        ColumnType::Boolean => column.boolean_code(field_str),
+                        ColumnType::State if is_empty => None,
+                        ColumnType::State => Some(column.state_code(field_str.trim())),
+                    };
+                    if value.is_none() && !is_empty {
+                        report.record_bad_cell(i, line, field);
+                    }
+                    (value, bad)
+                };
+                if timestamp.is_some() {
+                    column.push_sample(value, bad);
                 }
             }
+        }
+        piece
+    };
 
-            CsvRecord { timestamp, values }
-        })
+    // 2. Pipeline: parse the current block in parallel (CPU) while the next block is
+    // read (I/O). Only two raw blocks are ever alive, so memory tracks the columns.
+    let mut next = Vec::new();
+    let mut chunk_count = 0;
+    while !current.is_empty() {
+        let (pieces, read) = rayon::join(
+            || {
+                let rows_per_piece = current
+                    .len()
+                    .div_ceil(4 * rayon::current_num_threads())
+                    .max(1);
+                current
+                    .par_chunks(rows_per_piece)
+                    .map(|records| parse_records(records, &columns))
+                    .collect::<Vec<_>>()
+            },
+            || fill_chunk(&mut rdr, &mut next),
+        );
+        read?;
+
+        // 3. Append the pieces to the column buffers in order, one column per task
+        for piece in pieces {
+            timestamps.extend_from_slice(&piece.timestamps);
+            columns
+                .par_iter_mut()
+                .zip(piece.columns)
+                .for_each(|(column, mut part)| {
+                    part.recode_to(column);
+                    column.extend_from(&part);
+                });
+            report.absorb(piece.report);
+        }
+
+        std::mem::swap(&mut current, &mut next);
+        chunk_count += 1;
+    }
+    for (i, slot) in column_slots.iter().enumerate() {
+        if let Some(col) = slot {
+            columns[*col].unit = report.columns[i].unit.clone();
+        }
+    }
+
+    println!(
+        "Parsed {} rows in {} blocks, read_csv took: {:?}",
+        timestamps.len(),
+        chunk_count,
+        total_start.elapsed()
+    );
+
+    Ok((
+        ProcessedData {
+            timestamp_header,
+            timestamps,
+            columns,
+        },
+        report,
+    ))
+}
+
+/// Physical column positions of a long-format file.
+struct LongColumns {
+    tag: usize,
+    value: usize,
+    quality: Option<usize>,
+}
+
+/// One (timestamp, tag, value) sample of a long-format file. Bad samples have no
+/// value when they were given as a bad-value token.
+struct LongSample {
+    timestamp: DateTime<Utc>,
+    tag: String,
+    value: Option<f64>,
+    bad: bool,
+}
+
+fn read_long_records(
+    mut rdr: CsvReader,
+    path: &str,
+    header_list: Vec<String>,
+    long_columns: LongColumns,
+    layout: &LongLayout,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let total_start = Instant::now();
+
+    let mut report = new_file_report(path, &header_list);
+
+    let mut current = Vec::new();
+    fill_chunk(&mut rdr, &mut current)?;
+    let DetectedTimestamp {
+        column: ts_column,
+        parser: ts_parser,
+    } = detect_timestamp(path, &header_list, &current, options)?;
+    let timestamp_header = ts_column
+        .map(|c| c.header(&header_list))
+        .unwrap_or_else(|| "timestamp".to_string());
+    let filter = ColumnFilter::new(&options.columns)?;
+
+    let parse_row = |mut tally: BlockTally<LongSample>, raw_record: &csv::ByteRecord| {
+        let line = record_line(raw_record, options);
+        tally.report.total_rows += 1;
+        if raw_record.len() != header_list.len() {
+            tally.report.record_ragged_row(line, raw_record.len());
+        }
+        let field = |i: usize| {
+            raw_record
+                .get(i)
+                .and_then(|f| std::str::from_utf8(f).ok())
+                .unwrap_or("")
+        };
+
+        let mut bad = false;
+        if let Some(quality_idx) = long_columns.quality {
+            if !options.quality.is_good(field(quality_idx)) {
+                tally.report.bad_quality_rows += 1;
+                if !layout.keep_bad_quality {
+                    return tally;
+                }
+                bad = true;
+            }
+        }
+
+        let tag = field(long_columns.tag).trim();
+        if tag.is_empty() {
+            tally.report.columns[long_columns.tag].empty_cells += 1;
+            return tally;
+        }
+        if !filter.matches(tag) {
+            return tally;
+        }
+
+        let value_str = field(long_columns.value);
+        let is_token = options.quality.is_bad_value(value_str);
+        let value = if is_token {
+            tally.report.columns[long_columns.value].bad_quality_cells += 1;
+            bad = true;
+            None
+        } else {
+            options.parse_number(value_str)
+        };
+        if value.is_none() && !is_token {
+            if value_str.trim().is_empty() {
+                tally.report.columns[long_columns.value].empty_cells += 1;
+            } else {
+                tally
+                    .report
+                    .record_bad_cell(long_columns.value, line, value_str.as_bytes());
+            }
+            return tally;
+        }
+
+        let ts_str = ts_column.map(|c| c.text(raw_record)).unwrap_or_default();
+        let Some(timestamp) = ts_parser.parse(&ts_str) else {
+            if let Some(ts_column) = ts_column {
+                let ts_idx = ts_column.report_column();
+                if ts_str.trim().is_empty() {
+                    tally.report.columns[ts_idx].empty_cells += 1;
+                } else {
+                    tally
+                        .report
+                        .record_bad_cell(ts_idx, line, ts_str.as_bytes());
+                }
+            }
+            tally.report.missing_timestamp_rows += 1;
+            return tally;
+        };
+
+        tally.rows.push(LongSample {
+            timestamp,
+            tag: tag.to_string(),
+            value,
+            bad,
+        });
+        tally
+    };
+
+    // Same read/parse pipeline as the wide reader; samples are kept as
+    // (timestamp, column, value, bad) tuples until the timeline is known.
+    let mut tag_index: HashMap<String, usize> = HashMap::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut samples: Vec<(DateTime<Utc>, usize, Option<f64>, bool)> = Vec::new();
+    let mut next = Vec::new();
+    while !current.is_empty() {
+        let (block, read) = rayon::join(
+            || {
+                current
+                    .par_iter()
+                    .fold(|| BlockTally::new(header_list.len()), parse_row)
+                    .reduce(|| BlockTally::new(header_list.len()), BlockTally::merge)
+            },
+            || fill_chunk(&mut rdr, &mut next),
+        );
+        read?;
+
+        for sample in block.rows {
+            let col = match tag_index.get(&sample.tag) {
+                Some(&col) => col,
+                None => {
+                    tags.push(sample.tag.clone());
+                    tag_index.insert(sample.tag, tags.len() - 1);
+                    tags.len() - 1
+                }
+            };
+            samples.push((sample.timestamp, col, sample.value, sample.bad));
+        }
+        report.absorb(block.report);
+
+        std::mem::swap(&mut current, &mut next);
+    }
+
+    // Pivot: one column per tag on the sorted set of distinct timestamps
+    let mut timestamps: Vec<DateTime<Utc>> = samples.iter().map(|s| s.0).collect();
+    timestamps.par_sort_unstable();
+    timestamps.dedup();
+
+    let mut columns: Vec<SensorColumn> = tags
+        .into_iter()
+        .map(|tag| SensorColumn::missing(tag, timestamps.len()))
         .collect();
+    for (ts, col, value, bad) in samples {
+        let row = timestamps
+            .binary_search(&ts)
+            .expect("timeline contains every sample timestamp");
+        // Later samples for the same (timestamp, tag) win
+        columns[col].set_sample(row, value, bad);
+    }
 
-    println!("Parallel parsing took: {:?}", parse_start.elapsed());
-    println!("Total read_csv took: {:?}", total_start.elapsed());
+    println!(
+        "Pivoted {} tags over {} timestamps, read_csv took: {:?}",
+        columns.len(),
+        timestamps.len(),
+        total_start.elapsed()
+    );
 
-    Ok(ProcessedData {
-        headers: header_list,
-        rows: records,
-    })
+    Ok((
+        ProcessedData {
+            timestamp_header,
+            timestamps,
+            columns,
+        },
+        report,
+    ))
 }
 
-pub fn read_merge_csvs(paths: Vec<String>) -> Result<ProcessedData, String> {
+pub fn read_merge_csvs(
+    paths: Vec<String>,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, IngestReport), String> {
     if paths.is_empty() {
         return Err("No file paths provided".to_string());
     }
 
-    // 1. Read all files individually
-    let mut datasets = Vec::new();
-    for path in &paths {
-        datasets.push(read_csv(path)?);
-    }
+    // 1. Read all files in parallel, with zip archives expanded into their CSV members.
+    // Each read parallelises its own parsing too; rayon shares one pool between both.
+    let paths = archive::expand_archives(&paths)?;
+    let prefixes = options.naming.prefixes(&paths);
+    let read_start = Instant::now();
+    let results: Vec<(ProcessedData, FileReport)> = paths
+        .par_iter()
+        .zip(&prefixes)
+        .map(|(path, prefix)| {
+            let file_options = clock::file_options(options, path);
+            let (mut data, mut file_report) =
+                read_file(path, &clock::options_for_file(options, file_options))?;
+            if let Some(file_options) = file_options {
+                file_report.clock = clock::correct_timestamps(&mut data, file_options);
+            }
+            tag_columns(&mut data, path, prefix.as_deref());
+            Ok((data, file_report))
+        })
+        .collect::<Result<_, String>>()?;
+    println!("Read {} files in {:?}", results.len(), read_start.elapsed());
+    let mut report = IngestReport::default();
+    let datasets: Vec<ProcessedData> = results
+        .into_iter()
+        .map(|(data, file_report)| {
+            report.files.push(file_report);
+            data
+        })
+        .collect();
 
     if datasets.is_empty() {
         return Err("No data loaded".to_string());
     }
 
+    let datasets = align::align_datasets(datasets, &paths, &options.alignment)?;
+    let (data, conflicts) = merge_datasets(datasets, &paths, &options.conflict)?;
+    report.conflicts = conflicts;
+    Ok((data, report))
+}
+
+/// Name of a file without directories, compression suffix or extension.
+fn file_stem(path: &str) -> String {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    Path::new(archive::inner_name(name))
+        .file_stem()
+        .map_or_else(
+            || name.to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        )
+}
+
+/// Name of the directory holding a file, or for a zip member at the top of its
+/// archive, the archive's name.
+fn parent_name(path: &str) -> Option<String> {
+    let (archive, member) = match path.rsplit_once(archive::MEMBER_SEPARATOR) {
+        Some((archive, member)) => (Some(archive), member),
+        None => (None, path),
+    };
+    member
+        .rsplit(['/', '\\'])
+        .skip(1)
+        .find(|part| !part.is_empty())
+        .map(str::to_string)
+        .or_else(|| archive.map(file_stem))
+}
+
+/// Positions of `names` that another name equals, ignoring case.
+fn colliding(names: &[String]) -> Vec<bool> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            names
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other.eq_ignore_ascii_case(name))
+        })
+        .collect()
+}
+
+/// File name prefix of each path, qualified where names collide so that files with
+/// the same name never fold their columns together.
+fn file_prefixes(paths: &[String]) -> Vec<String> {
+    let mut prefixes: Vec<String> = paths.iter().map(|path| file_stem(path)).collect();
+    for (i, collides) in colliding(&prefixes).into_iter().enumerate() {
+        if let (true, Some(parent)) = (collides, parent_name(&paths[i])) {
+            prefixes[i] = format!("{}/{}", parent, prefixes[i]);
+        }
+    }
+    for (i, collides) in colliding(&prefixes).into_iter().enumerate() {
+        if collides {
+            prefixes[i] = format!("{}#{}", prefixes[i], i + 1);
+        }
+    }
+    prefixes
+}
+
+impl ColumnNaming {
+    /// Column prefix of each input, given after archives are expanded.
+    pub(crate) fn prefixes(&self, paths: &[String]) -> Vec<Option<String>> {
+        match self {
+            ColumnNaming::Merge => vec![None; paths.len()],
+            ColumnNaming::File => file_prefixes(paths).into_iter().map(Some).collect(),
+            ColumnNaming::Prefix { prefixes } => paths
+                .iter()
+                .map(|path| {
+                    prefixes
+                        .iter()
+                        .find(|(file, _)| is_named_file(path, file))
+                        .map(|(_, prefix)| prefix.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Records `path` as the source of every column of `data` and namespaces the columns
+/// by `prefix`, if any.
+pub(crate) fn tag_columns(data: &mut ProcessedData, path: &str, prefix: Option<&str>) {
+    for column in &mut data.columns {
+        column.add_source(path);
+        if let Some(prefix) = prefix {
+            column.tag.get_or_insert_with(|| column.name.clone());
+            column.name = format!("{}/{}", prefix, column.name);
+        }
+    }
+}
+
+/// Rows of one merged column where files disagreed.
+struct ConflictTally {
+    rows: Bitmap,
+    cells: usize,
+    first: usize,
+    last: usize,
+}
+
+impl ConflictTally {
+    fn record(&mut self, row: usize) {
+        if !self.rows.get(row) {
+            self.rows.set(row, true);
+            self.cells += 1;
+            self.first = self.first.min(row);
+            self.last = self.last.max(row);
+        }
+    }
+}
+
+/// Whether `source` is the file named by `file`, given as a full path or a file name.
+pub(crate) fn is_named_file(source: &str, file: &str) -> bool {
+    source == file
+        || Path::new(source)
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(file))
+}
+
+/// Timeline of time-sorted datasets by k-way merge: one pass over every row, with no
+/// re-sort of the union and no binary search per row.
+fn merge_sorted_timelines(datasets: &[ProcessedData]) -> (Vec<DateTime<Utc>>, Vec<Vec<usize>>) {
+    let total: usize = datasets.iter().map(|ds| ds.row_count()).sum();
+    let mut timestamps: Vec<DateTime<Utc>> = Vec::with_capacity(total);
+    let mut row_maps: Vec<Vec<usize>> = datasets
+        .iter()
+        .map(|ds| Vec::with_capacity(ds.row_count()))
+        .collect();
+
+    // Min-heap of each dataset's next timestamp; ties pop in dataset order
+    let mut heap: BinaryHeap<Reverse<(DateTime<Utc>, usize)>> = datasets
+        .iter()
+        .enumerate()
+        .filter_map(|(d, ds)| Some(Reverse((*ds.timestamps.first()?, d))))
+        .collect();
+    while let Some(Reverse((ts, d))) = heap.pop() {
+        if timestamps.last() != Some(&ts) {
+            timestamps.push(ts);
+        }
+        let row_map = &mut row_maps[d];
+        row_map.push(timestamps.len() - 1);
+        if let Some(&next) = datasets[d].timestamps.get(row_map.len()) {
+            heap.push(Reverse((next, d)));
+        }
+    }
+    (timestamps, row_maps)
+}
+
+/// Timeline of datasets in arbitrary order: sort the union, then locate every row.
+fn sort_timelines(datasets: &[ProcessedData]) -> (Vec<DateTime<Utc>>, Vec<Vec<usize>>) {
+    let mut timestamps: Vec<DateTime<Utc>> = datasets
+        .iter()
+        .flat_map(|ds| ds.timestamps.iter().copied())
+        .collect();
+    timestamps.par_sort_unstable();
+    timestamps.dedup();
+
+    let row_maps = datasets
+        .iter()
+        .map(|ds| {
+            ds.timestamps
+                .par_iter()
+                .map(|ts| {
+                    timestamps
+                        .binary_search(ts)
+                        .expect("timeline contains every file timestamp")
+                })
+                .collect()
+        })
+        .collect();
+    (timestamps, row_maps)
+}
+
+/// Merges datasets onto the sorted union of their timestamps. Columns are matched by
+/// name, case-insensitively; cells that several datasets have a value for are resolved
+/// by `policy`. `sources` names each dataset and returns the per-column conflicts.
+pub fn merge_datasets(
+    mut datasets: Vec<ProcessedData>,
+    sources: &[String],
+    policy: &ConflictPolicy,
+) -> Result<(ProcessedData, Vec<ColumnConflict>), String> {
     // 2. Determine global headers (Superset)
-    // Start with the first dataset's headers
+    // The timestamp column name from the first dataset is the canonical one; read_csv
+    // already keeps each file's timestamp out of its sensor columns.
+    let timestamp_header = datasets[0].timestamp_header.clone();
     let mut global_headers: Vec<String> = Vec::new();
-    let mut seen_headers: HashSet<String> = HashSet::new();
-
-    // Helper to check if a header is a timestamp
-    let is_timestamp = |h: &str| h.eq_ignore_ascii_case("timestamp") || h.eq_ignore_ascii_case("time");
-
-    // Find valid timestamp header from first dataset to be the canonical "timestamp" column
-    // Actually, we want to maintain the "timestamp" column at a specific position or simply identify it.
-    // Let's assume the first dataset has a valid timestamp column or we pick one.
-    // read_csv keeps all headers, including timestamp.
-
-    // Let's iterate datasets and merge headers.
-    // We treat "timestamp"/"time" as a special unique key column.
-    
-    // We will use the timestamp column name from the first dataset as the canonical one in global headers.
-    // If not found, default to "timestamp".
-    let canonical_ts_header = datasets[0].headers.iter()
-        .find(|h| is_timestamp(h))
-        .cloned()
-        .unwrap_or_else(|| "timestamp".to_string());
+    // Lower-cased name -> index in global_headers; the timestamp maps to nothing
+    let mut header_index: HashMap<String, Option<usize>> = HashMap::new();
+    header_index.insert(timestamp_header.to_lowercase(), None);
 
-    // Initialize global headers with canonical timestamp
-    global_headers.push(canonical_ts_header.clone());
-    seen_headers.insert(canonical_ts_header.clone().to_lowercase());
+    // Per dataset, the global column of each local column
+    let column_maps: Vec<Vec<Option<usize>>> = datasets
+        .iter()
+        .map(|ds| {
+            ds.columns
+                .iter()
+                .map(|col| {
+                    *header_index
+                        .entry(col.name.to_lowercase())
+                        .or_insert_with(|| {
+                            global_headers.push(col.name.clone());
+                            Some(global_headers.len() - 1)
+                        })
+                })
+                .collect()
+        })
+        .collect();
 
-    // Add other headers
-    for ds in &datasets {
-        for h in &ds.headers {
-            if is_timestamp(h) {
-                continue; // Already handled canonical timestamp
-            }
-            if !seen_headers.contains(&h.to_lowercase()) {
-                global_headers.push(h.clone());
-                seen_headers.insert(h.to_lowercase());
-            }
+    // 3. Build the shared timeline: the sorted, de-duplicated union of all timestamps,
+    // along with the timeline row of every local row
+    let timeline_start = Instant::now();
+    let sorted = datasets
+        .iter()
+        .all(|ds| ds.timestamps.windows(2).all(|w| w[0] <= w[1]));
+    let (timestamps, row_maps) = if sorted {
+        merge_sorted_timelines(&datasets)
+    } else {
+        sort_timelines(&datasets)
+    };
+    println!(
+        "Built timeline of {} rows ({}) in {:?}",
+        timestamps.len(),
+        if sorted { "k-way merge" } else { "sort" },
+        timeline_start.elapsed()
+    );
+
+    // 4. Scatter every file's columns onto the timeline
+    let mut columns: Vec<SensorColumn> = global_headers
+        .iter()
+        .map(|h| SensorColumn::missing(h.clone(), timestamps.len()))
+        .collect();
+
+    let mut order: Vec<usize> = (0..datasets.len()).collect();
+    if let ConflictPolicy::Prefer { file } = policy {
+        let preferred = |i: &usize| is_named_file(&sources[*i], file);
+        if !order.iter().any(preferred) {
+            return Err(format!("Preferred file {} is not among the inputs", file));
         }
+        // The preferred file is scattered last so its values overwrite the others
+        order.sort_by_key(preferred);
     }
 
-    // 3. Merge Rows
-    // Map: Timestamp -> Vector of Values (size = global_headers.len())
-    // Note: values in CsvRecord include the timestamp column as None.
-    // We need to map local column indices to global column indices.
+    let mut tallies: Vec<Option<ConflictTally>> = (0..columns.len()).map(|_| None).collect();
+    // Running (sum, count) of cells with more than one value, for `Mean`
+    let mut sums: HashMap<(usize, usize), (f64, u32)> = HashMap::new();
 
-    // Use BTreeMap to sort by timestamp automatically
-    let mut merged_map: BTreeMap<String, Vec<Option<f64>>> = BTreeMap::new();
+    for &d in &order {
+        let ds = &mut datasets[d];
+        let row_map = &row_maps[d];
 
-    for ds in &datasets {
-        // Build column mapping: local_idx -> global_idx
-        let mut col_map: Vec<usize> = Vec::with_capacity(ds.headers.len());
-        for h in &ds.headers {
-            if is_timestamp(h) {
-                // Map to the canonical timestamp index (0)
-                col_map.push(0);
-            } else {
-                // Find index in global_headers
-                // This is O(N*M), but N (headers) is small usually.
-                if let Some(pos) = global_headers.iter().position(|gh| gh.eq_ignore_ascii_case(h)) {
-                    col_map.push(pos);
-                } else {
-                     // Should not happen as we built global_headers from all headers
-                     col_map.push(0); // Fallback, though unsafe if logic wrong
-                }
+        for (col, global_idx) in ds.columns.iter_mut().zip(&column_maps[d]) {
+            // A column named like the timestamp has nowhere to go
+            let Some(global_idx) = *global_idx else {
+                continue;
+            };
+            let target = &mut columns[global_idx];
+            if col.kind_conflicts(target) {
+                return Err(format!(
+                    "Column {} holds {} in {} but {} in an earlier file",
+                    target.name,
+                    if col.is_categorical() {
+                        "labels"
+                    } else {
+                        "numbers"
+                    },
+                    sources[d],
+                    if target.is_categorical() {
+                        "labels"
+                    } else {
+                        "numbers"
+                    }
+                ));
             }
-        }
+            for source in &col.sources {
+                target.add_source(source);
+            }
+            if target.unit.is_none() {
+                target.unit = col.unit.clone();
+            }
+            if target.tag.is_none() {
+                target.tag = col.tag.clone();
+            }
+            // Files label states in their own order; code them the same way
+            col.recode_to(target);
+            for (local_idx, &row) in row_map.iter().enumerate() {
+                let bad = col.is_bad(local_idx);
+                let Some(v) = col.get(local_idx) else {
+                    // A bad-value token still flags an otherwise empty cell
+                    if bad && target.get(row).is_none() {
+                        target.mark_bad(row);
+                    }
+                    continue;
+                };
+                let Some(existing) = target.get(row) else {
+                    target.set_sample(row, Some(v), bad);
+                    continue;
+                };
 
-        for row in &ds.rows {
-            if let Some(ts) = &row.timestamp {
-                let entry = merged_map
-                    .entry(ts.clone())
-                    .or_insert_with(|| vec![None; global_headers.len()]);
-                
-                for (local_idx, val) in row.values.iter().enumerate() {
-                    if local_idx < col_map.len() {
-                         let global_idx = col_map[local_idx];
-                         // If we have a value, overwrite/fill.
-                         // Note: timestamp col in 'values' is None, so it won't overwrite valid data at global_idx 0 if we put data there.
-                         // But index 0 is canonical timestamp, and 'values' stores None for timestamp.
-                         if let Some(v) = val {
-                             entry[global_idx] = Some(*v);
-                         }
+                if existing.total_cmp(&v).is_ne() {
+                    if let ConflictPolicy::Fail = policy {
+                        let label = |value: f64| match target.states.get(value as usize) {
+                            Some(state) if target.is_categorical() => state.clone(),
+                            _ => value.to_string(),
+                        };
+                        return Err(format!(
+                            "Conflicting values for {} at {}: {} has {} where an earlier file has {}",
+                            target.name,
+                            timestamps[row],
+                            sources[d],
+                            label(v),
+                            label(existing)
+                        ));
                     }
+                    tallies[global_idx]
+                        .get_or_insert_with(|| ConflictTally {
+                            rows: Bitmap::unset(timestamps.len()),
+                            cells: 0,
+                            first: row,
+                            last: row,
+                        })
+                        .record(row);
+                }
+
+                match policy {
+                    ConflictPolicy::First => {}
+                    // States have no mean; they resolve as `Last`
+                    // The mean is bad if any value that went into it is
+                    ConflictPolicy::Mean if !target.is_categorical() => {
+                        let sum = sums.entry((global_idx, row)).or_insert((existing, 1));
+                        sum.0 += v;
+                        sum.1 += 1;
+                        if bad {
+                            target.mark_bad(row);
+                        }
+                    }
+                    _ => target.set_sample(row, Some(v), bad),
                 }
             }
         }
     }
 
-    // 4. Convert back to ProcessedData
-    let merged_rows: Vec<CsvRecord> = merged_map
+    for ((col, row), (sum, count)) in sums {
+        let bad = columns[col].is_bad(row);
+        columns[col].set_sample(row, Some(sum / count as f64), bad);
+    }
+
+    let conflicts: Vec<ColumnConflict> = tallies
         .into_iter()
-        .map(|(ts, values)| CsvRecord {
-            timestamp: Some(ts),
-            values,
+        .zip(&columns)
+        .filter_map(|(tally, column)| {
+            let tally = tally?;
+            Some(ColumnConflict {
+                column: column.name.clone(),
+                start: timestamps[tally.first],
+                end: timestamps[tally.last],
+                conflicting_cells: tally.cells,
+            })
         })
         .collect();
-
-    println!("Merged {} files. Total rows: {}", datasets.len(), merged_rows.len());
-    if !merged_rows.is_empty() {
-        println!("Timestamp Range: {:?} - {:?}", 
-            merged_rows.first().and_then(|r| r.timestamp.as_ref()), 
-            merged_rows.last().and_then(|r| r.timestamp.as_ref())
+    for conflict in &conflicts {
+        println!(
+            "{} conflicting cells in {} between {} and {}",
+            conflict.conflicting_cells, conflict.column, conflict.start, conflict.end
         );
     }
 
-    Ok(ProcessedData {
-        headers: global_headers,
-        rows: merged_rows,
-    })
-}
-
-pub fn load_metadata(path: &str) -> Result<Vec<SensorMetadata>, String> {
-    let file = File::open(path).map_err(|e| e.to_string())?;
-    let mut rdr = csv::Reader::from_reader(BufReader::new(file));
-    let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
-
-    // Map headers to indices case-insensitively
-    let mut tag_idx = None;
-    let mut desc_idx = None;
-    let mut unit_idx = None;
-    let mut comp_idx = None;
-
-    for (i, h) in headers.iter().enumerate() {
-        match h.trim().to_lowercase().as_str() {
-            "tag" => tag_idx = Some(i),
-            "description" => desc_idx = Some(i),
-            "unit" => unit_idx = Some(i),
-            "component" => comp_idx = Some(i),
-            _ => {}
-        }
-    }
-
-    // Require at least TAG to proceed? Or just try best effort.
-    // Allowing missing columns to be empty strings.
-
-    let mut metadata_list = Vec::new();
-
-    for result in rdr.records() {
-        let record = result.map_err(|e| e.to_string())?;
-
-        let tag = tag_idx
-            .and_then(|i| record.get(i))
-            .unwrap_or("")
-            .to_string();
-        // Skip empty rows or rows without a tag
-        if tag.trim().is_empty() {
-            continue;
-        }
-
-        let description = desc_idx
-            .and_then(|i| record.get(i))
-            .unwrap_or("")
-            .to_string();
-        let unit = unit_idx
-            .and_then(|i| record.get(i))
-            .unwrap_or("")
-            .to_string();
-        let component = comp_idx
-            .and_then(|i| record.get(i))
-            .unwrap_or("")
-            .to_string();
-
-        metadata_list.push(SensorMetadata {
-            tag,
-            description,
-            unit,
-            component,
-        });
+    println!(
+        "Merged {} files. Total rows: {}",
+        datasets.len(),
+        timestamps.len()
+    );
+    if !timestamps.is_empty() {
+        println!(
+            "Timestamp Range: {:?} - {:?}",
+            timestamps.first(),
+            timestamps.last()
+        );
     }
 
-    Ok(metadata_list)
+    Ok((
+        ProcessedData {
+            timestamp_header,
+            timestamps,
+            columns,
+        },
+        conflicts,
+    ))
 }
 
 #[allow(dead_code)]
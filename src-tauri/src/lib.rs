@@ -1,33 +1,187 @@
+mod align;
+mod archive;
+mod asset_tree;
+mod clock;
+mod column_store;
 mod csv_processor;
-use csv_processor::{load_metadata, CsvMetadata, ProcessedData, SensorMetadata};
-use serde::Deserialize;
-use std::sync::Mutex;
-use tauri::{Emitter, State};
+mod metadata;
+mod parquet_io;
+mod preview;
+mod quality;
+mod search;
+mod select;
+mod states;
+mod tail;
+mod xlsx_reader;
+use asset_tree::{AssetNode, AssetSummary};
+use chrono::{DateTime, TimeZone, Utc};
+use column_store::{ColumnType, ProcessedData, SensorColumn};
+use csv_processor::{CsvMetadata, DataChunk, ImportOptions, IngestReport};
+use metadata::{load_metadata, MetadataStore, MetadataValidation, SensorMetadata};
+use search::SensorMatch;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager, State};
 
 struct SessionData {
     data: ProcessedData,
     paths: Vec<String>,
+    options: ImportOptions,
+    /// Length of each input before it was loaded; where following resumes.
+    file_lengths: Vec<Option<u64>>,
+    report: IngestReport,
+    /// Set to stop the follow thread, if one is running.
+    follow_stop: Option<Arc<AtomicBool>>,
 }
 
-struct AppState(Mutex<Option<SessionData>>);
+impl SessionData {
+    fn stop_following(&mut self) {
+        if let Some(stop) = self.follow_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn asset_tree(&self, metadata: &MetadataStore) -> AssetNode {
+        AssetNode::build(&self.data.columns, metadata)
+    }
+}
+
+struct AppState {
+    session: Mutex<Option<SessionData>>,
+    /// Metadata of the last metadata file loaded; kept across data reloads.
+    metadata: Mutex<MetadataStore>,
+}
 
 #[tauri::command]
-fn load_csv(paths: Vec<String>, state: State<AppState>) -> Result<CsvMetadata, String> {
-    let data = csv_processor::read_merge_csvs(paths.clone())?;
+fn load_csv(
+    paths: Vec<String>,
+    options: Option<ImportOptions>,
+    state: State<AppState>,
+) -> Result<CsvMetadata, String> {
+    let options = options.unwrap_or_default();
+    let (data, report) = csv_processor::read_merge_csvs(paths.clone(), &options)?;
+    let file_lengths = tail::file_lengths(&paths, &report);
     let metadata = CsvMetadata {
-        headers: data.headers.clone(),
-        total_rows: data.rows.len(),
+        headers: data.headers(),
+        total_rows: data.row_count(),
+        report: report.clone(),
     };
 
-    let mut state_lock = state.0.lock().map_err(|e| e.to_string())?;
-    *state_lock = Some(SessionData { data, paths });
+    let mut state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    if let Some(previous) = state_lock.as_mut() {
+        previous.stop_following();
+    }
+    *state_lock = Some(SessionData {
+        data,
+        paths,
+        options,
+        file_lengths,
+        report,
+        follow_stop: None,
+    });
 
     Ok(metadata)
 }
 
+/// Polls the loaded CSV files every `interval_ms` (default 1000) and merges rows
+/// appended to them into the session. Each batch of new rows is emitted as a
+/// "data-stream-append" chunk holding every sensor. Returns the followed paths.
+#[tauri::command]
+fn follow_files(
+    interval_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<String>, String> {
+    let mut state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_mut().ok_or("No data loaded")?;
+    session.stop_following();
+
+    let mut followers = Vec::new();
+    for (path, length) in session.paths.iter().zip(&session.file_lengths) {
+        let Some(length) = length else { continue };
+        let clock = session
+            .report
+            .files
+            .iter()
+            .find(|f| &f.path == path)
+            .and_then(|f| f.clock.clone());
+        let follower = tail::Follower::new(path, &session.paths, *length, &session.options, clock)?;
+        if let Some(follower) = follower {
+            followers.push((path.clone(), follower));
+        }
+    }
+    let followed: Vec<String> = followers.iter().map(|(path, _)| path.clone()).collect();
+    if followers.is_empty() {
+        return Ok(followed);
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    session.follow_stop = Some(stop.clone());
+    let interval = Duration::from_millis(interval_ms.unwrap_or(1000).max(50));
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+
+        // Parse outside the lock so get_data is never held up by a slow read
+        let mut appended = Vec::new();
+        for (path, follower) in &mut followers {
+            match follower.poll() {
+                Ok(Some(data)) if data.row_count() > 0 => appended.push((path.clone(), data)),
+                Ok(_) => {}
+                Err(e) => println!("Failed to read appended rows from {}: {}", path, e),
+            }
+        }
+        if appended.is_empty() {
+            continue;
+        }
+
+        let state = app.state::<AppState>();
+        let Ok(mut state_lock) = state.session.lock() else {
+            break;
+        };
+        // A new load replaces the session and stops this thread
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let Some(session) = state_lock.as_mut() else {
+            break;
+        };
+        for (path, data) in appended {
+            let policy = &session.options.conflict;
+            let chunk = match tail::append_rows(&mut session.data, &path, data, policy) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    println!("Failed to merge appended rows from {}: {}", path, e);
+                    continue;
+                }
+            };
+            if let Err(e) = app.emit("data-stream-append", chunk) {
+                println!("Failed to emit appended rows: {}", e);
+            }
+        }
+    });
+
+    Ok(followed)
+}
+
+#[tauri::command]
+fn stop_following(state: State<AppState>) -> Result<(), String> {
+    let mut state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    if let Some(session) = state_lock.as_mut() {
+        session.stop_following();
+    }
+    Ok(())
+}
+
 #[tauri::command]
 fn get_loaded_paths(state: State<AppState>) -> Result<Vec<String>, String> {
-    let state_lock = state.0.lock().map_err(|e| e.to_string())?;
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
     match &*state_lock {
         Some(session) => Ok(session.paths.clone()),
         None => Ok(Vec::new()),
@@ -36,46 +190,60 @@ fn get_loaded_paths(state: State<AppState>) -> Result<Vec<String>, String> {
 
 #[tauri::command]
 fn get_data(
-    sensors: Vec<String>,
+    mut sensors: Vec<String>,
+    asset: Option<String>,
+    exclude_bad: Option<bool>,
     window: tauri::Window,
     state: State<AppState>,
 ) -> Result<(), String> {
-    let state_lock = state.0.lock().map_err(|e| e.to_string())?;
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
     let session = state_lock.as_ref().ok_or("No data loaded")?;
     let data = &session.data;
 
-    // Find indices of requested sensors
-    let mut indices = Vec::new();
-    for sensor in &sensors {
-        if let Some(idx) = data.headers.iter().position(|h| h == sensor) {
-            indices.push(idx);
+    // Sensors under `asset` follow the listed ones
+    if let Some(asset) = asset {
+        let metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+        let tree = session.asset_tree(&metadata_lock);
+        let node = tree
+            .find(&asset)
+            .ok_or_else(|| format!("Asset {} not found", asset))?;
+        for sensor in node.all_sensors() {
+            if !sensors.contains(&sensor) {
+                sensors.push(sensor);
+            }
         }
     }
 
+    // Find column indices of requested sensors; unknown names stream as empty
+    // values so the chunk columns stay aligned with `sensors`
+    let indices: Vec<Option<usize>> = sensors.iter().map(|s| data.column_index(s)).collect();
+
+    // Boolean and state sensors stream their codes; the state tables go first so the
+    // frontend can label them and draw them as step series
+    let states: Vec<ColumnStates> = indices
+        .iter()
+        .flatten()
+        .map(|&c| &data.columns[c])
+        .filter(|c| c.is_categorical())
+        .map(ColumnStates::of)
+        .collect();
+    window
+        .emit("data-stream-states", states)
+        .map_err(|e| e.to_string())?;
+
     // Using chunks to stream data
     // Chunk size 5000 seems reasonable for UI responsiveness vs IPC overhead
     const CHUNK_SIZE: usize = 5000;
 
     // Notify start (optional, but good for UI loading state if needed)
-    // window.emit("data-stream-start", data.rows.len()).map_err(|e| e.to_string())?;
+    // window.emit("data-stream-start", data.row_count()).map_err(|e| e.to_string())?;
 
-    for (_chunk_idx, chunk) in data.rows.chunks(CHUNK_SIZE).enumerate() {
-        let chunk_data: Vec<csv_processor::CsvRecord> = chunk
-            .iter()
+    for start in (0..data.row_count()).step_by(CHUNK_SIZE) {
+        let end = (start + CHUNK_SIZE).min(data.row_count());
+        // Bad-quality samples stream flagged, or as empty values when excluded
+        let chunk_data: Vec<csv_processor::CsvRecord> = (start..end)
             .map(|row| {
-                let mut new_values = Vec::new();
-                for &idx in &indices {
-                    if idx < row.values.len() {
-                        new_values.push(row.values[idx]);
-                    } else {
-                        new_values.push(None);
-                    }
-                }
-
-                csv_processor::CsvRecord {
-                    timestamp: row.timestamp.clone(),
-                    values: new_values,
-                }
+                csv_processor::CsvRecord::of_row(data, &indices, row, exclude_bad.unwrap_or(false))
             })
             .collect();
 
@@ -83,7 +251,7 @@ fn get_data(
         window
             .emit(
                 "data-stream-chunk",
-                ProcessedData {
+                DataChunk {
                     headers: sensors.clone(),
                     rows: chunk_data,
                 },
@@ -102,16 +270,169 @@ fn get_data(
     Ok(())
 }
 
+/// Writes the session, or the chosen sensors within `[start, end]`, to a Parquet file.
+#[tauri::command]
+fn export_parquet(
+    path: String,
+    sensors: Option<Vec<String>>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    parquet_io::write_parquet(&path, &session.data, sensors.as_deref(), start, end)
+}
+
+/// Reads the first `rows` rows (100 by default) of a CSV file and reports the detected
+/// delimiter, timestamp column and format, and inferred column types, without loading it.
+#[tauri::command]
+fn preview_csv(
+    path: String,
+    options: Option<ImportOptions>,
+    rows: Option<usize>,
+) -> Result<preview::CsvPreview, String> {
+    preview::preview_csv(
+        &path,
+        &options.unwrap_or_default(),
+        rows.unwrap_or(preview::DEFAULT_PREVIEW_ROWS),
+    )
+}
+
+/// Worksheet names of an Excel workbook, so the user can pick one before import.
+#[tauri::command]
+fn list_xlsx_sheets(path: String) -> Result<Vec<String>, String> {
+    xlsx_reader::list_sheets(&path)
+}
+
+/// Source files of one session column.
+#[derive(Debug, Serialize)]
+struct ColumnSources {
+    column: String,
+    sources: Vec<String>,
+}
+
+/// Type and state table of a boolean or state column; its values index `states`.
+#[derive(Debug, Serialize, Clone)]
+struct ColumnStates {
+    column: String,
+    column_type: ColumnType,
+    states: Vec<String>,
+}
+
+impl ColumnStates {
+    fn of(column: &SensorColumn) -> Self {
+        ColumnStates {
+            column: column.name.clone(),
+            column_type: column.column_type,
+            states: column.states.clone(),
+        }
+    }
+}
+
+#[tauri::command]
+fn get_column_sources(state: State<AppState>) -> Result<Vec<ColumnSources>, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    Ok(session
+        .data
+        .columns
+        .iter()
+        .map(|c| ColumnSources {
+            column: c.name.clone(),
+            sources: c.sources.clone(),
+        })
+        .collect())
+}
+
 #[tauri::command]
 fn get_all_sensors(state: State<AppState>) -> Result<Vec<String>, String> {
-    let state_lock = state.0.lock().map_err(|e| e.to_string())?;
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    Ok(session.data.headers())
+}
+
+#[tauri::command]
+fn load_metadata_command(
+    path: String,
+    options: Option<ImportOptions>,
+    state: State<AppState>,
+) -> Result<Vec<SensorMetadata>, String> {
+    let metadata_list = load_metadata(&path, &options.unwrap_or_default())?;
+    let mut metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+    *metadata_lock = MetadataStore::new(metadata_list.clone());
+    Ok(metadata_list)
+}
+
+/// Compares the stored metadata with the loaded sensors, matching tags ignoring case,
+/// and counts samples outside each sensor's engineering range.
+#[tauri::command]
+fn validate_metadata(state: State<AppState>) -> Result<MetadataValidation, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    let metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+    Ok(metadata_lock.validate(&session.data))
+}
+
+/// Metadata of each loaded sensor that has an entry, keyed by sensor name.
+#[tauri::command]
+fn get_sensor_metadata(state: State<AppState>) -> Result<HashMap<String, SensorMetadata>, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    let metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+    Ok(session
+        .data
+        .columns
+        .iter()
+        .filter_map(|c| Some((c.name.clone(), metadata_lock.get(c.tag())?.clone())))
+        .collect())
+}
+
+/// Child nodes of the asset at `path`, or the top-level assets when no path is given.
+#[tauri::command]
+fn get_asset_children(
+    path: Option<String>,
+    state: State<AppState>,
+) -> Result<Vec<AssetSummary>, String> {
+    let node = get_asset_subtree(path, state)?;
+    Ok(node.children.iter().map(AssetNode::summary).collect())
+}
+
+/// All sensors at the asset at `path` and below.
+#[tauri::command]
+fn get_asset_sensors(path: String, state: State<AppState>) -> Result<Vec<String>, String> {
+    Ok(get_asset_subtree(Some(path), state)?.all_sensors())
+}
+
+/// The asset at `path` with all its descendants, or the whole tree when no path is given.
+#[tauri::command]
+fn get_asset_subtree(path: Option<String>, state: State<AppState>) -> Result<AssetNode, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
     let session = state_lock.as_ref().ok_or("No data loaded")?;
-    Ok(session.data.headers.clone())
+    let metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+    let tree = session.asset_tree(&metadata_lock);
+    let path = path.unwrap_or_default();
+    tree.find(&path)
+        .cloned()
+        .ok_or_else(|| format!("Asset {} not found", path))
 }
 
+/// Loaded sensors matching `query`, best first; see `search::search_sensors` for the
+/// query syntax.
 #[tauri::command]
-fn load_metadata_command(path: String) -> Result<Vec<SensorMetadata>, String> {
-    load_metadata(&path)
+fn search_sensors(
+    query: String,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<Vec<SensorMatch>, String> {
+    let state_lock = state.session.lock().map_err(|e| e.to_string())?;
+    let session = state_lock.as_ref().ok_or("No data loaded")?;
+    let metadata_lock = state.metadata.lock().map_err(|e| e.to_string())?;
+    let mut matches = search::search_sensors(&query, &session.data.columns, &metadata_lock)?;
+    if let Some(limit) = limit {
+        matches.truncate(limit);
+    }
+    Ok(matches)
 }
 
 use tauri_plugin_shell::process::CommandEvent;
@@ -120,7 +441,7 @@ use tauri_plugin_shell::ShellExt;
 #[tauri::command]
 async fn run_python_analysis(app: tauri::AppHandle) -> Result<String, String> {
     // Use mock data for testing
-    let data = ProcessedData {
+    let data = DataChunk {
         headers: vec![
             "Timestamp".to_string(),
             "SensorA".to_string(),
@@ -128,16 +449,19 @@ async fn run_python_analysis(app: tauri::AppHandle) -> Result<String, String> {
         ],
         rows: vec![
             csv_processor::CsvRecord {
-                timestamp: Some("2021-01-01T00:00:00Z".to_string()),
+                timestamp: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).single(),
                 values: vec![None, Some(10.0), Some(20.0)],
+                bad: Vec::new(),
             },
             csv_processor::CsvRecord {
-                timestamp: Some("2021-01-01T01:00:00Z".to_string()),
+                timestamp: Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).single(),
                 values: vec![None, Some(15.0), Some(25.0)],
+                bad: Vec::new(),
             },
             csv_processor::CsvRecord {
-                timestamp: Some("2021-01-01T02:00:00Z".to_string()),
+                timestamp: Utc.with_ymd_and_hms(2021, 1, 1, 2, 0, 0).single(),
                 values: vec![None, Some(12.0), Some(22.0)],
+                bad: Vec::new(),
             },
         ],
     };
@@ -183,8 +507,11 @@ async fn run_python_analysis(app: tauri::AppHandle) -> Result<String, String> {
 #[derive(Debug, Deserialize)]
 struct SingleOperation {
     #[serde(rename = "type")]
-    op_type: String, // 'add', 'subtract', 'multiply', 'divide', 'power'
+    op_type: String, // 'add', 'subtract', 'multiply', 'divide', 'power', 'equals'
+    #[serde(default)]
     value: f64,
+    /// State label compared by 'equals' on a boolean or state sensor.
+    state: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -204,6 +531,10 @@ struct SensorOperationConfig {
     multi_op: Option<MultiOperation>,
     #[serde(rename = "customName")]
     custom_name: Option<String>,
+    /// Treat bad-quality samples as missing. Otherwise a derived value is flagged bad
+    /// when any of its inputs is.
+    #[serde(rename = "excludeBad", default)]
+    exclude_bad: bool,
 }
 
 #[tauri::command]
@@ -212,7 +543,7 @@ fn calculate_new_sensor(
     config: SensorOperationConfig,
     state: State<AppState>,
 ) -> Result<String, String> {
-    let mut state_lock = state.0.lock().map_err(|e| e.to_string())?;
+    let mut state_lock = state.session.lock().map_err(|e| e.to_string())?;
     let session = state_lock.as_mut().ok_or("No data loaded")?;
     let data = &mut session.data;
 
@@ -224,14 +555,40 @@ fn calculate_new_sensor(
     // Identify indices
     let mut indices = Vec::new();
     for sensor in &sensors {
-        match data.headers.iter().position(|h| h == sensor) {
+        match data.column_index(sensor) {
             Some(idx) => indices.push(idx),
             None => return Err(format!("Sensor not found: {}", sensor)),
         }
     }
+    // State codes are arbitrary, so state sensors only support 'equals'
+    let is_equals = config
+        .single_op
+        .as_ref()
+        .is_some_and(|op| op.op_type == "equals");
+    if !is_equals {
+        if let Some(&idx) = indices
+            .iter()
+            .find(|&&idx| data.columns[idx].column_type == ColumnType::State)
+        {
+            return Err(format!(
+                "{} is a state sensor; compare it to a state with 'equals'",
+                data.columns[idx].name
+            ));
+        }
+    }
 
     // Determine new sensor name and logic
     let mut new_sensor_name;
+    let mut new_column = SensorColumn::with_capacity(String::new(), data.row_count());
+    let exclude_bad = config.exclude_bad;
+    let value = |idx: usize, row: usize| {
+        let column = &data.columns[idx];
+        if exclude_bad {
+            column.get_good(row)
+        } else {
+            column.get(row)
+        }
+    };
 
     if config.mode == "single" {
         if sensors.len() != 1 {
@@ -244,31 +601,57 @@ fn calculate_new_sensor(
             "multiply" => "*",
             "divide" => "/",
             "power" => "^",
+            "equals" => "=",
             _ => return Err("Invalid single operation type".to_string()),
         };
         new_sensor_name = format!("{} {} {}", sensors[0], op_symbol, op.value);
 
-        // Calculation Loop
-        for row in &mut data.rows {
-            let val = row.values[indices[0]];
-            let new_val = match val {
-                Some(v) => match op.op_type.as_str() {
-                    "add" => Some(v + op.value),
-                    "subtract" => Some(v - op.value),
-                    "multiply" => Some(v * op.value),
-                    "divide" => {
-                        if op.value != 0.0 {
-                            Some(v / op.value)
-                        } else {
-                            None
-                        }
-                    } // Handle div by zero?
-                    "power" => Some(v.powf(op.value)),
-                    _ => None,
-                },
-                None => None,
+        // Comparisons yield a boolean sensor, true where the value or state matches
+        if op.op_type == "equals" {
+            let source = &data.columns[indices[0]];
+            let target = match (&op.state, source.is_categorical()) {
+                (Some(state), true) => {
+                    new_sensor_name = format!("{} = {}", sensors[0], state);
+                    let code = source
+                        .states
+                        .iter()
+                        .position(|s| s.eq_ignore_ascii_case(state))
+                        .ok_or_else(|| format!("{} has no state {}", sensors[0], state))?;
+                    Some(code as f64)
+                }
+                (Some(_), false) => {
+                    return Err(format!("{} is not a state sensor", sensors[0]));
+                }
+                (None, _) => Some(op.value),
             };
-            row.values.push(new_val);
+            new_column = new_column.with_type(ColumnType::Boolean);
+            for row in 0..data.row_count() {
+                let matched = value(indices[0], row).map(|v| Some(v) == target);
+                new_column.push(matched.map(|m| if m { 1.0 } else { 0.0 }));
+            }
+        } else {
+            // Calculation Loop
+            for row in 0..data.row_count() {
+                let val = value(indices[0], row);
+                let new_val = match val {
+                    Some(v) => match op.op_type.as_str() {
+                        "add" => Some(v + op.value),
+                        "subtract" => Some(v - op.value),
+                        "multiply" => Some(v * op.value),
+                        "divide" => {
+                            if op.value != 0.0 {
+                                Some(v / op.value)
+                            } else {
+                                None
+                            }
+                        } // Handle div by zero?
+                        "power" => Some(v.powf(op.value)),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                new_column.push(new_val);
+            }
         }
     } else if config.mode == "multi" {
         let op = config.multi_op.ok_or("Missing multiOp config")?;
@@ -294,7 +677,7 @@ fn calculate_new_sensor(
         }
 
         // Calculation Loop
-        for row in &mut data.rows {
+        for row in 0..data.row_count() {
             let mut valid_values = Vec::new();
             let mut base_val = None;
 
@@ -311,7 +694,7 @@ fn calculate_new_sensor(
                 let mut count = 0;
 
                 for (i, sensor_name) in sensors.iter().enumerate() {
-                    let val_opt = row.values[indices[i]];
+                    let val_opt = value(indices[i], row);
                     if let Some(v) = val_opt {
                         if sensor_name == base_sensor {
                             base_val = Some(v);
@@ -336,11 +719,11 @@ fn calculate_new_sensor(
                     }
                     None => None,
                 };
-                row.values.push(new_val);
+                new_column.push(new_val);
             } else {
                 // Aggregation
                 for &idx in &indices {
-                    if let Some(v) = row.values[idx] {
+                    if let Some(v) = value(idx, row) {
                         valid_values.push(v);
                     }
                 }
@@ -366,7 +749,7 @@ fn calculate_new_sensor(
                         _ => None,
                     }
                 };
-                row.values.push(new_val);
+                new_column.push(new_val);
             }
         }
     } else {
@@ -380,8 +763,22 @@ fn calculate_new_sensor(
         }
     }
 
-    // Update Headers
-    data.headers.push(new_sensor_name.clone());
+    if !exclude_bad {
+        for row in 0..data.row_count() {
+            if indices.iter().any(|&idx| data.columns[idx].is_bad(row)) {
+                new_column.mark_bad(row);
+            }
+        }
+    }
+
+    // Store the derived column; it comes from wherever its inputs did
+    new_column.name = new_sensor_name.clone();
+    for &idx in &indices {
+        for source in data.columns[idx].sources.clone() {
+            new_column.add_source(&source);
+        }
+    }
+    data.columns.push(new_column);
 
     Ok(new_sensor_name)
 }
@@ -392,15 +789,30 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
-        .manage(AppState(Mutex::new(None)))
+        .manage(AppState {
+            session: Mutex::new(None),
+            metadata: Mutex::new(MetadataStore::default()),
+        })
         .invoke_handler(tauri::generate_handler![
             load_csv,
             get_data,
             get_all_sensors,
             load_metadata_command,
+            validate_metadata,
+            get_sensor_metadata,
+            get_asset_children,
+            get_asset_sensors,
+            get_asset_subtree,
+            search_sensors,
             run_python_analysis,
             get_loaded_paths,
-            calculate_new_sensor
+            calculate_new_sensor,
+            export_parquet,
+            list_xlsx_sheets,
+            follow_files,
+            stop_following,
+            get_column_sources,
+            preview_csv
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,425 @@
+use crate::column_store::{ColumnType, ProcessedData};
+use crate::csv_processor::{open_csv_reader, ImportOptions};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// One tag of a metadata file. Columns other than the known fields are kept in
+/// `attributes`, keyed by header.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SensorMetadata {
+    pub tag: String,
+    pub description: String,
+    pub unit: String,
+    pub component: String,
+    /// Engineering range of the instrument. A zero and span are read as
+    /// `low_limit = zero` and `high_limit = zero + span`.
+    pub low_limit: Option<f64>,
+    pub high_limit: Option<f64>,
+    pub low_alarm: Option<f64>,
+    pub high_alarm: Option<f64>,
+    pub low_low_alarm: Option<f64>,
+    pub high_high_alarm: Option<f64>,
+    /// Expected time between samples in milliseconds.
+    pub sample_period_ms: Option<f64>,
+    pub data_type: Option<ColumnType>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// Known metadata fields, each with the headers it is read from, most specific first.
+/// Headers are compared after lower-casing and dropping everything but letters and digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Tag,
+    Description,
+    Unit,
+    Component,
+    LowLimit,
+    HighLimit,
+    /// Width of the range, added to the low limit.
+    Span,
+    LowAlarm,
+    HighAlarm,
+    LowLowAlarm,
+    HighHighAlarm,
+    SamplePeriod,
+    /// Samples per second, inverted into a period.
+    SampleRate,
+    DataType,
+}
+
+const FIELD_HEADERS: [(Field, &[&str]); 14] = [
+    (Field::Tag, &["tag", "tagname", "name", "sensor"]),
+    (Field::Description, &["description", "desc"]),
+    (Field::Unit, &["unit", "units", "eu", "engineeringunits"]),
+    (Field::Component, &["component", "equipment", "asset"]),
+    (
+        Field::LowLimit,
+        &[
+            "lowlimit",
+            "lolimit",
+            "eulow",
+            "rangelow",
+            "lowerlimit",
+            "min",
+            "zero",
+        ],
+    ),
+    (
+        Field::HighLimit,
+        &[
+            "highlimit",
+            "hilimit",
+            "euhigh",
+            "rangehigh",
+            "upperlimit",
+            "max",
+        ],
+    ),
+    (Field::Span, &["span"]),
+    (
+        Field::LowAlarm,
+        &["lowalarm", "loalarm", "alarmlow", "alarmlo", "lo"],
+    ),
+    (
+        Field::HighAlarm,
+        &["highalarm", "hialarm", "alarmhigh", "alarmhi", "hi"],
+    ),
+    (
+        Field::LowLowAlarm,
+        &["lowlowalarm", "loloalarm", "alarmlolo", "lolo"],
+    ),
+    (
+        Field::HighHighAlarm,
+        &["highhighalarm", "hihialarm", "alarmhihi", "hihi"],
+    ),
+    (
+        Field::SamplePeriod,
+        &[
+            "sampleperiod",
+            "sampleinterval",
+            "scanrate",
+            "scantime",
+            "period",
+        ],
+    ),
+    (
+        Field::SampleRate,
+        &["samplerate", "samplingrate", "frequency"],
+    ),
+    (Field::DataType, &["datatype", "type", "pointtype"]),
+];
+
+/// The field `header` names, with the rank of its spelling among the field's headers.
+fn field_of(header: &str) -> Option<(Field, usize)> {
+    let key: String = header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    FIELD_HEADERS.iter().find_map(|(field, names)| {
+        let rank = names.iter().position(|name| *name == key)?;
+        Some((*field, rank))
+    })
+}
+
+/// Reads a sample period such as "500 ms", "5 min" or "10 Hz" into milliseconds;
+/// bare numbers are seconds.
+fn parse_sample_period(value: &str, options: &ImportOptions) -> Option<f64> {
+    let (number, unit) = options.parse_number_unit(value)?;
+    let millis = match unit.map(str::to_lowercase).as_deref() {
+        None | Some("s") | Some("sec") | Some("secs") | Some("seconds") => number * 1000.0,
+        Some("ms") => number,
+        Some("min") | Some("mins") | Some("minutes") => number * 60_000.0,
+        Some("h") | Some("hr") | Some("hours") => number * 3_600_000.0,
+        Some("hz") if number > 0.0 => 1000.0 / number,
+        _ => return None,
+    };
+    Some(millis)
+}
+
+/// Reads a sample rate such as "10 Hz" or "2" into a period in milliseconds; bare
+/// numbers are hertz. Values given with a time unit are read as periods.
+fn parse_sample_rate(value: &str, options: &ImportOptions) -> Option<f64> {
+    let (number, unit) = options.parse_number_unit(value)?;
+    let hertz = match unit.map(str::to_lowercase).as_deref() {
+        None | Some("hz") | Some("/s") => number,
+        Some("khz") => number * 1000.0,
+        Some("/min") => number / 60.0,
+        _ => return parse_sample_period(value, options),
+    };
+    (hertz > 0.0).then(|| 1000.0 / hertz)
+}
+
+fn parse_data_type(value: &str) -> Option<ColumnType> {
+    let value = value.trim().to_lowercase();
+    let numeric = [
+        "float", "double", "real", "int", "integer", "numeric", "analog", "number",
+    ];
+    if numeric.iter().any(|t| value.starts_with(t)) {
+        Some(ColumnType::Numeric)
+    } else if ["bool", "boolean", "digital", "binary", "bit"].contains(&value.as_str()) {
+        Some(ColumnType::Boolean)
+    } else if ["string", "text", "enum", "state", "discrete"].contains(&value.as_str()) {
+        Some(ColumnType::State)
+    } else {
+        None
+    }
+}
+
+/// Reads a metadata file with one row per tag. Limits, alarm limits and the sample
+/// period are parsed with the number format of `options`; cells of known fields that
+/// do not parse, and all other columns, are kept in `attributes`.
+pub fn load_metadata(path: &str, options: &ImportOptions) -> Result<Vec<SensorMetadata>, String> {
+    let mut rdr = open_csv_reader(path, options)?;
+    let headers: Vec<String> = rdr
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    // Each field is read from the column with its most specific header, the first of
+    // those on ties, so "Tag" wins over an earlier "Name"; the other columns are attributes
+    let ranked: Vec<Option<(Field, usize)>> = headers.iter().map(|h| field_of(h)).collect();
+    let fields: Vec<Option<Field>> = ranked
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let (field, rank) = (*column)?;
+            let best = ranked
+                .iter()
+                .enumerate()
+                .filter_map(|(j, other)| match other {
+                    Some((f, r)) if *f == field => Some((*r, j)),
+                    _ => None,
+                })
+                .min()?;
+            (best == (rank, i)).then_some(field)
+        })
+        .collect();
+    if !fields.contains(&Some(Field::Tag)) {
+        return Err(format!("{}: no tag column found", path));
+    }
+
+    let mut metadata_list = Vec::new();
+    for result in rdr.records() {
+        let record = result.map_err(|e| e.to_string())?;
+        let mut metadata = SensorMetadata::default();
+        let mut span = None;
+        for (i, value) in record.iter().enumerate().take(headers.len()) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let number = || options.parse_number(value);
+            let parsed = match fields[i] {
+                Some(Field::Tag) => {
+                    metadata.tag = value.to_string();
+                    true
+                }
+                Some(Field::Description) => {
+                    metadata.description = value.to_string();
+                    true
+                }
+                Some(Field::Unit) => {
+                    metadata.unit = value.to_string();
+                    true
+                }
+                Some(Field::Component) => {
+                    metadata.component = value.to_string();
+                    true
+                }
+                Some(Field::LowLimit) => {
+                    metadata.low_limit = number();
+                    metadata.low_limit.is_some()
+                }
+                Some(Field::HighLimit) => {
+                    metadata.high_limit = number();
+                    metadata.high_limit.is_some()
+                }
+                Some(Field::Span) => {
+                    span = number();
+                    span.is_some()
+                }
+                Some(Field::LowAlarm) => {
+                    metadata.low_alarm = number();
+                    metadata.low_alarm.is_some()
+                }
+                Some(Field::HighAlarm) => {
+                    metadata.high_alarm = number();
+                    metadata.high_alarm.is_some()
+                }
+                Some(Field::LowLowAlarm) => {
+                    metadata.low_low_alarm = number();
+                    metadata.low_low_alarm.is_some()
+                }
+                Some(Field::HighHighAlarm) => {
+                    metadata.high_high_alarm = number();
+                    metadata.high_high_alarm.is_some()
+                }
+                Some(Field::SamplePeriod) => {
+                    metadata.sample_period_ms = parse_sample_period(value, options);
+                    metadata.sample_period_ms.is_some()
+                }
+                Some(Field::SampleRate) => {
+                    metadata.sample_period_ms = parse_sample_rate(value, options);
+                    metadata.sample_period_ms.is_some()
+                }
+                Some(Field::DataType) => {
+                    metadata.data_type = parse_data_type(value);
+                    metadata.data_type.is_some()
+                }
+                None => false,
+            };
+            if !parsed {
+                metadata
+                    .attributes
+                    .insert(headers[i].clone(), value.to_string());
+            }
+        }
+        // An explicit high limit wins over zero + span
+        if let (None, Some(span)) = (metadata.high_limit, span) {
+            metadata.high_limit = Some(metadata.low_limit.unwrap_or(0.0) + span);
+        }
+        // Skip empty rows or rows without a tag
+        if metadata.tag.is_empty() {
+            continue;
+        }
+        metadata_list.push(metadata);
+    }
+
+    Ok(metadata_list)
+}
+
+/// Key tags and sensor names are matched by: trimmed and lower-cased.
+fn tag_key(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Metadata of the session, joined to sensors by tag ignoring case. A tag listed more
+/// than once resolves to its first entry.
+#[derive(Default)]
+pub struct MetadataStore {
+    entries: Vec<SensorMetadata>,
+    index: HashMap<String, usize>,
+    duplicate_tags: Vec<String>,
+}
+
+/// How a metadata file lines up with the loaded sensors.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct MetadataValidation {
+    /// Sensors in the data without a metadata entry.
+    pub missing_metadata: Vec<String>,
+    /// Metadata tags matching no sensor.
+    pub missing_data: Vec<String>,
+    /// Tags listed more than once, as first spelled.
+    pub duplicate_tags: Vec<String>,
+    /// Numeric sensors with samples outside the engineering range of their metadata.
+    pub out_of_range: Vec<RangeViolation>,
+}
+
+/// Samples of one sensor outside its engineering range.
+#[derive(Debug, Serialize, Clone)]
+pub struct RangeViolation {
+    pub sensor: String,
+    pub below_low: usize,
+    pub above_high: usize,
+    /// Timestamps of the first and last sample out of range.
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl MetadataStore {
+    pub fn new(entries: Vec<SensorMetadata>) -> Self {
+        let mut index = HashMap::new();
+        let mut duplicate_tags = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let key = tag_key(&entry.tag);
+            match index.get(&key) {
+                None => {
+                    index.insert(key, i);
+                }
+                Some(&first) => {
+                    let tag = entries[first].tag.clone();
+                    if !duplicate_tags.contains(&tag) {
+                        duplicate_tags.push(tag);
+                    }
+                }
+            }
+        }
+        MetadataStore {
+            entries,
+            index,
+            duplicate_tags,
+        }
+    }
+
+    pub fn get(&self, sensor: &str) -> Option<&SensorMetadata> {
+        self.index.get(&tag_key(sensor)).map(|&i| &self.entries[i])
+    }
+
+    pub fn validate(&self, data: &ProcessedData) -> MetadataValidation {
+        let sensor_keys: HashSet<String> = data.columns.iter().map(|c| tag_key(c.tag())).collect();
+        let missing_metadata = data
+            .columns
+            .iter()
+            .filter(|c| self.get(c.tag()).is_none())
+            .map(|c| c.name.clone())
+            .collect();
+        let missing_data = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(i, entry)| {
+                let key = tag_key(&entry.tag);
+                self.index[&key] == *i && !sensor_keys.contains(&key)
+            })
+            .map(|(_, entry)| entry.tag.clone())
+            .collect();
+        MetadataValidation {
+            missing_metadata,
+            missing_data,
+            duplicate_tags: self.duplicate_tags.clone(),
+            out_of_range: self.out_of_range(data),
+        }
+    }
+
+    /// Counts the samples of each numeric sensor below its low limit or above its high
+    /// limit. Bad samples are not counted; a reading the source flagged is expected to
+    /// be off.
+    fn out_of_range(&self, data: &ProcessedData) -> Vec<RangeViolation> {
+        data.columns
+            .iter()
+            .filter(|c| !c.is_categorical())
+            .filter_map(|column| {
+                let metadata = self.get(column.tag())?;
+                let low = metadata.low_limit.unwrap_or(f64::NEG_INFINITY);
+                let high = metadata.high_limit.unwrap_or(f64::INFINITY);
+                let (mut below_low, mut above_high) = (0, 0);
+                let (mut first, mut last) = (None, 0);
+                for row in 0..data.row_count() {
+                    let Some(value) = column.get_good(row) else {
+                        continue;
+                    };
+                    if value < low {
+                        below_low += 1;
+                    } else if value > high {
+                        above_high += 1;
+                    } else {
+                        continue;
+                    }
+                    first.get_or_insert(row);
+                    last = row;
+                }
+                Some(RangeViolation {
+                    sensor: column.name.clone(),
+                    below_low,
+                    above_high,
+                    start: data.timestamps[first?],
+                    end: data.timestamps[last],
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,467 @@
+use crate::column_store::{ColumnType, ProcessedData, SensorColumn};
+use crate::csv_processor::{
+    find_timestamp_column, new_file_report, FileReport, ImportOptions, TimestampParser,
+};
+use crate::select::ColumnFilter;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, TimestampMicrosecondType};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, Utc};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Rows per record batch, both when reading and when writing.
+const BATCH_ROWS: usize = 64 * 1024;
+/// Rows read from a column to decide whether it holds quality codes.
+const SAMPLE_ROWS: usize = 200;
+/// Field metadata key holding a sensor's unit.
+const UNIT_KEY: &str = "unit";
+
+fn is_temporal(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Timestamp(..) | DataType::Date32 | DataType::Date64
+    )
+}
+
+fn is_numeric(data_type: &DataType) -> bool {
+    data_type.is_numeric() || matches!(data_type, DataType::Boolean)
+}
+
+fn is_text(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => true,
+        DataType::Dictionary(_, values) => is_text(values),
+        _ => false,
+    }
+}
+
+fn column_type(data_type: &DataType) -> ColumnType {
+    if is_text(data_type) {
+        ColumnType::State
+    } else if matches!(data_type, DataType::Boolean) {
+        ColumnType::Boolean
+    } else {
+        ColumnType::Numeric
+    }
+}
+
+/// One sensor column of a record batch, cast for reading.
+enum BatchValues {
+    Numbers(Float64Array),
+    Labels(StringArray),
+}
+
+/// Reads a Parquet file with a timestamp column and numeric, boolean or string sensor
+/// columns; string columns are read as states. Columns of any other type are skipped.
+/// Quality columns are paired with their value columns as in wide CSV files.
+pub fn read_parquet(
+    path: &str,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let total_start = Instant::now();
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|e| e.to_string())?;
+    let schema = builder.schema().clone();
+    let names: Vec<String> = schema.fields().iter().map(|f| f.name().clone()).collect();
+
+    // A column named like a timestamp wins, otherwise the first temporal column
+    let timestamp_idx = find_timestamp_column(&names, &options.timestamp)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .or_else(|| {
+            schema
+                .fields()
+                .iter()
+                .position(|f| is_temporal(f.data_type()))
+        })
+        .ok_or_else(|| format!("{}: no timestamp column found", path))?;
+
+    let filter = ColumnFilter::new(&options.columns)?;
+    let quality_columns = options
+        .quality
+        .pair_columns(&names, |i| sample_column(path, i).into_iter())
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let value_indices: Vec<usize> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(i, f)| {
+            *i != timestamp_idx
+                && !quality_columns.is_quality[*i]
+                && (is_numeric(f.data_type()) || is_text(f.data_type()))
+                && filter.matches(f.name())
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let skipped: Vec<&str> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(i, f)| {
+            *i != timestamp_idx
+                && !value_indices.contains(i)
+                && !quality_columns.is_quality[*i]
+                && filter.matches(f.name())
+        })
+        .map(|(_, f)| f.name().as_str())
+        .collect();
+    if !skipped.is_empty() {
+        println!("Skipping unsupported Parquet columns: {:?}", skipped);
+    }
+
+    // Only the timestamp, selected columns and their quality columns are decoded;
+    // batches hold them in file order
+    let mut projection: Vec<usize> = value_indices.clone();
+    projection.extend(value_indices.iter().filter_map(|&i| quality_columns.of[i]));
+    projection.push(timestamp_idx);
+    projection.sort_unstable();
+    projection.dedup();
+    let batch_index = |i: usize| {
+        projection
+            .binary_search(&i)
+            .expect("projection contains every column read")
+    };
+    let mask = ProjectionMask::roots(builder.parquet_schema(), projection.iter().copied());
+
+    let mut report = new_file_report(path, &names);
+    let mut timestamps: Vec<DateTime<Utc>> = Vec::new();
+    let mut columns: Vec<SensorColumn> = value_indices
+        .iter()
+        .map(|&i| {
+            let field = schema.field(i);
+            let mut column =
+                SensorColumn::new(names[i].clone()).with_type(column_type(field.data_type()));
+            column.unit = field.metadata().get(UNIT_KEY).cloned();
+            column
+        })
+        .collect();
+
+    let reader = builder
+        .with_batch_size(BATCH_ROWS)
+        .with_projection(mask)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut ts_parser: Option<TimestampParser> = None;
+    for batch in reader {
+        let batch = batch.map_err(|e| e.to_string())?;
+        let first_row = report.total_rows;
+        report.total_rows += batch.num_rows();
+
+        let ts_column = batch.column(batch_index(timestamp_idx));
+        let parser = match ts_parser.take() {
+            Some(parser) => parser,
+            None => detect_parser(ts_column, options)?,
+        };
+        let batch_timestamps = timestamps_of(ts_column, &parser)?;
+        ts_parser = Some(parser);
+
+        let values: Vec<BatchValues> = value_indices
+            .iter()
+            .zip(&columns)
+            .map(|(&i, column)| {
+                let array = batch.column(batch_index(i));
+                Ok(if column.column_type == ColumnType::State {
+                    let strings =
+                        arrow_cast::cast(array, &DataType::Utf8).map_err(|e| e.to_string())?;
+                    BatchValues::Labels(strings.as_string::<i32>().clone())
+                } else {
+                    let numbers =
+                        arrow_cast::cast(array, &DataType::Float64).map_err(|e| e.to_string())?;
+                    BatchValues::Numbers(numbers.as_primitive::<Float64Type>().clone())
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let qualities: Vec<Option<StringArray>> = value_indices
+            .iter()
+            .map(|&i| {
+                let Some(q) = quality_columns.of[i] else {
+                    return Ok(None);
+                };
+                let strings = arrow_cast::cast(batch.column(batch_index(q)), &DataType::Utf8)
+                    .map_err(|e| e.to_string())?;
+                Ok(Some(strings.as_string::<i32>().clone()))
+            })
+            .collect::<Result<_, String>>()?;
+
+        for (row, ts) in batch_timestamps.into_iter().enumerate() {
+            let Some(ts) = ts else {
+                if ts_column.is_null(row) {
+                    report.columns[timestamp_idx].empty_cells += 1;
+                } else {
+                    let raw = arrow_cast::display::array_value_to_string(ts_column, row)
+                        .unwrap_or_default();
+                    report.record_bad_cell(
+                        timestamp_idx,
+                        (first_row + row + 1) as u64,
+                        raw.as_bytes(),
+                    );
+                }
+                report.missing_timestamp_rows += 1;
+                continue;
+            };
+            timestamps.push(ts);
+            for (c, array) in values.iter().enumerate() {
+                let i = value_indices[c];
+                if let BatchValues::Labels(labels) = array {
+                    if labels.is_valid(row) && options.quality.is_bad_value(labels.value(row)) {
+                        report.columns[i].bad_quality_cells += 1;
+                        columns[c].push_sample(None, true);
+                        continue;
+                    }
+                }
+                let value = match array {
+                    BatchValues::Numbers(numbers) if numbers.is_valid(row) => {
+                        Some(numbers.value(row))
+                    }
+                    BatchValues::Labels(labels) if labels.is_valid(row) => {
+                        Some(columns[c].state_code(labels.value(row)))
+                    }
+                    _ => None,
+                };
+                if value.is_none() {
+                    report.columns[i].empty_cells += 1;
+                }
+                let bad = qualities[c]
+                    .as_ref()
+                    .is_some_and(|q| q.is_valid(row) && !options.quality.is_good(q.value(row)));
+                if bad {
+                    report.columns[i].bad_quality_cells += 1;
+                }
+                columns[c].push_sample(value, bad);
+            }
+        }
+    }
+
+    println!(
+        "Read {} rows from Parquet, read_parquet took: {:?}",
+        timestamps.len(),
+        total_start.elapsed()
+    );
+
+    Ok((
+        ProcessedData {
+            timestamp_header: names[timestamp_idx].clone(),
+            timestamps,
+            columns,
+        },
+        report,
+    ))
+}
+
+/// Leading non-null values of one column as text, for telling quality columns from
+/// readings. Empty when the column cannot be read.
+fn sample_column(path: &str, i: usize) -> Vec<String> {
+    let first_batch = || -> Result<Option<RecordBatch>, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).map_err(|e| e.to_string())?;
+        let mask = ProjectionMask::roots(builder.parquet_schema(), [i]);
+        let mut reader = builder
+            .with_batch_size(SAMPLE_ROWS)
+            .with_projection(mask)
+            .build()
+            .map_err(|e| e.to_string())?;
+        reader.next().transpose().map_err(|e| e.to_string())
+    };
+    let Ok(Some(batch)) = first_batch() else {
+        return Vec::new();
+    };
+    let Ok(strings) = arrow_cast::cast(batch.column(0), &DataType::Utf8) else {
+        return Vec::new();
+    };
+    strings
+        .as_string::<i32>()
+        .iter()
+        .flatten()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// String timestamp columns get their format detected from the first batch.
+fn detect_parser(column: &ArrayRef, options: &ImportOptions) -> Result<TimestampParser, String> {
+    let parser = TimestampParser::new(&options.timestamp)?;
+    if !matches!(column.data_type(), DataType::Utf8 | DataType::LargeUtf8) {
+        return Ok(parser);
+    }
+    let strings = arrow_cast::cast(column, &DataType::Utf8).map_err(|e| e.to_string())?;
+    Ok(parser.detect(
+        strings.as_string::<i32>().iter().flatten(),
+        options.timestamp.day_first,
+    ))
+}
+
+fn timestamps_of(
+    column: &ArrayRef,
+    parser: &TimestampParser,
+) -> Result<Vec<Option<DateTime<Utc>>>, String> {
+    match column.data_type() {
+        DataType::Timestamp(_, tz) => {
+            let micros = arrow_cast::cast(
+                column,
+                &DataType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+            )
+            .map_err(|e| e.to_string())?;
+            // Zone-less timestamps are wall-clock times in the configured source zone
+            let naive = tz.is_none();
+            Ok(micros
+                .as_primitive::<TimestampMicrosecondType>()
+                .iter()
+                .map(|v| {
+                    let dt = DateTime::from_timestamp_micros(v?)?;
+                    if naive {
+                        parser.localize(dt.naive_utc())
+                    } else {
+                        Some(dt)
+                    }
+                })
+                .collect())
+        }
+        DataType::Date32 | DataType::Date64 => {
+            let micros =
+                arrow_cast::cast(column, &DataType::Timestamp(TimeUnit::Microsecond, None))
+                    .map_err(|e| e.to_string())?;
+            Ok(micros
+                .as_primitive::<TimestampMicrosecondType>()
+                .iter()
+                .map(|v| parser.localize(DateTime::from_timestamp_micros(v?)?.naive_utc()))
+                .collect())
+        }
+        // Strings and epoch numbers go through the same parser as CSV cells
+        _ => {
+            let strings = arrow_cast::cast(column, &DataType::Utf8).map_err(|e| e.to_string())?;
+            Ok(strings
+                .as_string::<i32>()
+                .iter()
+                .map(|v| parser.parse(v?))
+                .collect())
+        }
+    }
+}
+
+/// Writes the selected sensors (all when `sensors` is `None`) between `start` and
+/// `end` inclusive to a Snappy-compressed Parquet file. Sensors with bad-quality
+/// samples get a "<name> Quality" column of "Good"/"Bad" after them, which
+/// `read_parquet` pairs back. Returns the number of rows written.
+pub fn write_parquet(
+    path: &str,
+    data: &ProcessedData,
+    sensors: Option<&[String]>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<usize, String> {
+    let indices: Vec<usize> = match sensors {
+        Some(sensors) => sensors
+            .iter()
+            .map(|s| {
+                data.column_index(s)
+                    .ok_or_else(|| format!("Sensor not found: {}", s))
+            })
+            .collect::<Result<_, _>>()?,
+        None => (0..data.columns.len()).collect(),
+    };
+
+    // Timestamps are sorted, so the range is a contiguous slice
+    let first = start.map_or(0, |s| data.timestamps.partition_point(|t| *t < s));
+    let last = end.map_or(data.row_count(), |e| {
+        data.timestamps.partition_point(|t| *t <= e)
+    });
+    let last = last.max(first);
+
+    let mut fields = vec![Field::new(
+        data.timestamp_header.clone(),
+        DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        false,
+    )];
+    // Boolean columns are written as booleans and state columns as their labels
+    for &c in &indices {
+        let column = &data.columns[c];
+        let data_type = match column.column_type {
+            ColumnType::Numeric => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::State => DataType::Utf8,
+        };
+        let metadata: HashMap<String, String> = column
+            .unit
+            .iter()
+            .map(|unit| (UNIT_KEY.to_string(), unit.clone()))
+            .collect();
+        fields.push(Field::new(column.name.clone(), data_type, true).with_metadata(metadata));
+        if column.has_bad() {
+            fields.push(Field::new(
+                format!("{} Quality", column.name),
+                DataType::Utf8,
+                true,
+            ));
+        }
+    }
+    let schema = Arc::new(Schema::new(fields));
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer =
+        ArrowWriter::try_new(file, schema.clone(), Some(props)).map_err(|e| e.to_string())?;
+
+    for batch_start in (first..last).step_by(BATCH_ROWS) {
+        let batch_end = (batch_start + BATCH_ROWS).min(last);
+        let mut arrays: Vec<ArrayRef> = Vec::with_capacity(indices.len() + 1);
+        arrays.push(Arc::new(
+            TimestampMicrosecondArray::from(
+                data.timestamps[batch_start..batch_end]
+                    .iter()
+                    .map(|t| t.timestamp_micros())
+                    .collect::<Vec<i64>>(),
+            )
+            .with_timezone("UTC"),
+        ));
+        for &c in &indices {
+            let column = &data.columns[c];
+            let rows = batch_start..batch_end;
+            let array: ArrayRef = match column.column_type {
+                ColumnType::Numeric => Arc::new(Float64Array::from(
+                    rows.clone().map(|row| column.get(row)).collect::<Vec<_>>(),
+                )),
+                ColumnType::Boolean => Arc::new(BooleanArray::from(
+                    rows.clone()
+                        .map(|row| column.get(row).map(|v| v != 0.0))
+                        .collect::<Vec<_>>(),
+                )),
+                ColumnType::State => Arc::new(StringArray::from(
+                    rows.clone()
+                        .map(|row| column.state_label(row))
+                        .collect::<Vec<_>>(),
+                )),
+            };
+            arrays.push(array);
+            if column.has_bad() {
+                arrays.push(Arc::new(StringArray::from(
+                    rows.map(|row| {
+                        if column.is_bad(row) {
+                            Some("Bad")
+                        } else {
+                            column.get(row).map(|_| "Good")
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+                )));
+            }
+        }
+        let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(|e| e.to_string())?;
+        writer.write(&batch).map_err(|e| e.to_string())?;
+    }
+
+    writer.close().map_err(|e| e.to_string())?;
+    println!("Exported {} rows to {}", last - first, path);
+    Ok(last - first)
+}
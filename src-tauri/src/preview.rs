@@ -0,0 +1,194 @@
+use crate::archive;
+use crate::column_store::ColumnType;
+use crate::csv_processor::{
+    decode_input, locate_timestamp_column, open_csv_reader, ImportOptions, TimestampFormat,
+    TimestampParser,
+};
+use crate::states::infer_column_type;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+/// Rows read by `preview_csv` when the caller does not ask for a number.
+pub const DEFAULT_PREVIEW_ROWS: usize = 100;
+/// Sample values returned per column.
+const PREVIEW_SAMPLES: usize = 5;
+/// Lines inspected when sniffing the delimiter.
+const SNIFF_LINES: usize = 20;
+const DELIMITER_CANDIDATES: [char; 4] = [',', ';', '\t', '|'];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    Numeric,
+    Boolean,
+    /// Text with few distinct values, loaded as a state column.
+    State,
+    Text,
+    /// No non-empty cell in the previewed rows.
+    Empty,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnPreview {
+    pub name: String,
+    pub kind: ColumnKind,
+    /// The first few non-empty values, as they appear in the file.
+    pub samples: Vec<String>,
+    /// Unit written after the numbers of a numeric column, e.g. "°C".
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvPreview {
+    pub delimiter: char,
+    pub headers: Vec<String>,
+    pub timestamp_column: Option<String>,
+    /// Format the timestamp column parses as; `None` when no sample parsed.
+    pub timestamp_format: Option<TimestampFormat>,
+    /// Previewed rows whose timestamp did not parse with the detected format.
+    pub unparsed_timestamps: usize,
+    pub rows_read: usize,
+    /// One entry per header, the timestamp column included.
+    pub columns: Vec<ColumnPreview>,
+}
+
+/// Occurrences of `delimiter` in `line` outside quoted fields.
+fn count_delimiters(line: &str, delimiter: char, quote: char) -> usize {
+    let mut quoted = false;
+    line.chars()
+        .filter(|&c| {
+            if c == quote {
+                quoted = !quoted;
+            }
+            c == delimiter && !quoted
+        })
+        .count()
+}
+
+/// Picks the candidate that splits the header and most following lines into the same
+/// number of fields, preferring more fields on a tie.
+fn sniff_delimiter(lines: &[String], quote: char) -> Option<char> {
+    DELIMITER_CANDIDATES
+        .iter()
+        .filter_map(|&delimiter| {
+            let counts: Vec<usize> = lines
+                .iter()
+                .map(|line| count_delimiters(line, delimiter, quote))
+                .collect();
+            let header = *counts.first()?;
+            if header == 0 {
+                return None;
+            }
+            let consistent = counts.iter().filter(|&&c| c == header).count();
+            Some(((consistent, header), delimiter))
+        })
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, delimiter)| delimiter)
+}
+
+fn infer_kind(values: &[&str], options: &ImportOptions) -> ColumnKind {
+    if values.is_empty() {
+        return ColumnKind::Empty;
+    }
+    match infer_column_type(values.iter().copied(), options) {
+        ColumnType::Boolean => ColumnKind::Boolean,
+        ColumnType::State => ColumnKind::State,
+        ColumnType::Numeric if values.iter().all(|v| options.parse_number(v).is_some()) => {
+            ColumnKind::Numeric
+        }
+        ColumnType::Numeric => ColumnKind::Text,
+    }
+}
+
+/// Reads the first `rows` records of a CSV file and reports how it would be imported:
+/// the sniffed delimiter, the timestamp column and format, and a type per column.
+/// `options.delimiter` is only used when no candidate delimiter fits.
+pub fn preview_csv(path: &str, options: &ImportOptions, rows: usize) -> Result<CsvPreview, String> {
+    let mut reader = decode_input(archive::open_input(path)?, options)?;
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    while lines.len() < options.header_row + SNIFF_LINES {
+        line.clear();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+    let sniff_from = options.header_row.min(lines.len());
+    let delimiter =
+        sniff_delimiter(&lines[sniff_from..], options.quote).unwrap_or(options.delimiter);
+
+    let options = ImportOptions {
+        delimiter,
+        ..options.clone()
+    };
+    let mut rdr = open_csv_reader(path, &options)?;
+    let headers = rdr.headers().map_err(|e| e.to_string())?;
+    let header_list: Vec<String> = headers.iter().map(|s| s.trim().to_string()).collect();
+
+    let records: Vec<csv::StringRecord> = rdr
+        .records()
+        .take(rows)
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let block: Vec<csv::ByteRecord> = records.iter().map(|r| r.as_byte_record().clone()).collect();
+    let ts_column = locate_timestamp_column(&header_list, &block, &options)?;
+    let mut timestamp_format = None;
+    let mut unparsed_timestamps = 0;
+    if let Some(ts_column) = ts_column {
+        let texts: Vec<_> = block.iter().map(|r| ts_column.text(r)).collect();
+        let parser = TimestampParser::new(&options.timestamp)?.detect(
+            texts.iter().map(|text| text.as_ref()),
+            options.timestamp.day_first,
+        );
+        unparsed_timestamps = texts
+            .iter()
+            .filter(|text| parser.parse(text).is_none())
+            .count();
+        if unparsed_timestamps < records.len() {
+            timestamp_format = parser.format();
+        }
+    }
+
+    let columns = header_list
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let values: Vec<&str> = records
+                .iter()
+                .filter_map(|r| r.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect();
+            let kind = infer_kind(&values, &options);
+            let unit = match kind {
+                ColumnKind::Numeric => values
+                    .iter()
+                    .find_map(|v| options.parse_number_unit(v)?.1)
+                    .map(str::to_string),
+                _ => None,
+            };
+            ColumnPreview {
+                name: name.clone(),
+                kind,
+                unit,
+                samples: values
+                    .iter()
+                    .take(PREVIEW_SAMPLES)
+                    .map(|v| v.to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(CsvPreview {
+        delimiter,
+        timestamp_column: ts_column.map(|c| c.header(&header_list)),
+        headers: header_list,
+        timestamp_format,
+        unparsed_timestamps,
+        rows_read: records.len(),
+        columns,
+    })
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Header endings that mark a column as a candidate quality of the column named by the
+/// rest, compared case-insensitively, e.g. `FlowRate Quality` or `FlowRate.Quality`.
+const QUALITY_SUFFIXES: [&str; 3] = [" quality", "_quality", ".quality"];
+
+/// Non-empty values of a candidate column checked before it is paired by suffix.
+const QUALITY_SAMPLE_SIZE: usize = 200;
+
+/// Quality words historians write besides the good and bad-value lists.
+const QUALITY_WORDS: [&str; 3] = ["Bad", "Uncertain", "Questionable"];
+
+/// Per-value quality: status columns paired with value columns, the quality values
+/// counted as good there and in long-format quality columns, and tokens historians
+/// write into value cells in place of a reading.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct QualityOptions {
+    /// Quality column of each value column, keyed by value column header. Columns
+    /// named like a value column plus " Quality", "_Quality" or ".Quality" are paired
+    /// without being listed when their values are quality codes.
+    pub columns: HashMap<String, String>,
+    /// Quality values accepted as good, compared case-insensitively. Empty quality
+    /// cells count as good; everything else flags the value as bad.
+    pub good: Vec<String>,
+    /// Cell values, compared case-insensitively, that stand for a bad sample rather
+    /// than a reading.
+    #[serde(rename = "badValues")]
+    pub bad_values: Vec<String>,
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        QualityOptions {
+            columns: HashMap::new(),
+            // "192" is the OPC DA code for Good
+            good: vec!["Good".to_string(), "192".to_string()],
+            bad_values: [
+                "Bad",
+                "Bad Input",
+                "Bad Data",
+                "I/O Timeout",
+                "Comm Fail",
+                "Scan Off",
+                "Shutdown",
+                "No Data",
+                "Calc Failed",
+                "#N/A",
+                "#VALUE!",
+                "#DIV/0!",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// Quality pairing of a header list. A value whose quality column is not good is
+/// kept, flagged bad.
+pub struct QualityColumns {
+    /// Quality column of each column, by header position.
+    pub of: Vec<Option<usize>>,
+    /// Whether each column is a quality column, read only alongside its value column.
+    pub is_quality: Vec<bool>,
+}
+
+impl QualityOptions {
+    /// Whether a quality code counts as good; an empty code does.
+    pub fn is_good(&self, quality: &str) -> bool {
+        let quality = quality.trim();
+        quality.is_empty() || self.good.iter().any(|g| g.eq_ignore_ascii_case(quality))
+    }
+
+    pub fn is_bad_value(&self, value: &str) -> bool {
+        let value = value.trim();
+        !value.is_empty()
+            && self
+                .bad_values
+                .iter()
+                .any(|b| b.eq_ignore_ascii_case(value))
+    }
+
+    /// Whether `values` read as quality codes: every one is good, a bad-value token, a
+    /// quality word or an OPC DA quality byte, and at least one is given.
+    fn are_quality_codes(&self, values: impl Iterator<Item = String>) -> bool {
+        let is_code = |value: &str| {
+            self.good.iter().any(|g| g.eq_ignore_ascii_case(value))
+                || self.is_bad_value(value)
+                || QUALITY_WORDS.iter().any(|w| w.eq_ignore_ascii_case(value))
+                // Bits 7-6 of an OPC DA quality are 00 bad, 01 uncertain or 11 good
+                || value.parse::<u8>().is_ok_and(|code| code >> 6 != 0b10)
+        };
+        let mut values = values
+            .filter(|v| !v.trim().is_empty())
+            .take(QUALITY_SAMPLE_SIZE)
+            .peekable();
+        values.peek().is_some() && values.all(|v| is_code(v.trim()))
+    }
+
+    /// Pairs value columns with their quality columns, listed ones first and then by
+    /// suffix. `sample` gives leading values of a column, so that a suffixed column
+    /// holding readings rather than quality codes stays a sensor. Errors when a listed
+    /// column is missing.
+    pub fn pair_columns<I: Iterator<Item = String>>(
+        &self,
+        header_list: &[String],
+        sample: impl Fn(usize) -> I,
+    ) -> Result<QualityColumns, String> {
+        let find = |name: &str| {
+            header_list
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(name.trim()))
+        };
+        let mut of = vec![None; header_list.len()];
+        for (value, quality) in &self.columns {
+            let value_idx = find(value).ok_or_else(|| format!("column {} not found", value))?;
+            let quality_idx =
+                find(quality).ok_or_else(|| format!("quality column {} not found", quality))?;
+            of[value_idx] = Some(quality_idx);
+        }
+        for (i, header) in header_list.iter().enumerate() {
+            let lower = header.to_lowercase();
+            let Some(value) = QUALITY_SUFFIXES
+                .iter()
+                .find_map(|suffix| lower.strip_suffix(suffix))
+            else {
+                continue;
+            };
+            let Some(value_idx) = find(value).filter(|&v| v != i && of[v].is_none()) else {
+                continue;
+            };
+            if self.are_quality_codes(sample(i)) {
+                of[value_idx] = Some(i);
+            }
+        }
+
+        let mut is_quality = vec![false; header_list.len()];
+        for &quality_idx in of.iter().flatten() {
+            is_quality[quality_idx] = true;
+        }
+        Ok(QualityColumns { of, is_quality })
+    }
+}
@@ -0,0 +1,272 @@
+use crate::column_store::SensorColumn;
+use crate::metadata::MetadataStore;
+use crate::select::glob_to_regex;
+use regex::Regex;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// Sensor field a query is matched against.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Tag,
+    Description,
+    Unit,
+    Component,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 4] = [
+        SearchField::Tag,
+        SearchField::Description,
+        SearchField::Unit,
+        SearchField::Component,
+    ];
+
+    fn of_filter(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "tag" | "name" => Some(SearchField::Tag),
+            "description" | "desc" => Some(SearchField::Description),
+            "unit" | "units" => Some(SearchField::Unit),
+            "component" | "asset" => Some(SearchField::Component),
+            _ => None,
+        }
+    }
+
+    /// How much a match in this field counts relative to one in the tag.
+    fn weight(self) -> f64 {
+        match self {
+            SearchField::Tag => 1.0,
+            SearchField::Description => 0.6,
+            SearchField::Component => 0.5,
+            SearchField::Unit => 0.4,
+        }
+    }
+}
+
+/// Characters `[start, end)` of a field matched by the query, for highlighting.
+#[derive(Debug, Serialize, Clone)]
+pub struct MatchSpan {
+    pub field: SearchField,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SensorMatch {
+    pub sensor: String,
+    pub description: String,
+    /// Unit from the metadata, or else the one written after the values.
+    pub unit: String,
+    pub component: String,
+    pub score: f64,
+    pub spans: Vec<MatchSpan>,
+}
+
+struct Filter {
+    field: SearchField,
+    pattern: Regex,
+}
+
+/// Lower-cases a character without changing the character count.
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Splits a query on whitespace, keeping double-quoted runs together.
+fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn is_word_start(text: &[char], i: usize) -> bool {
+    i == 0
+        || !text[i - 1].is_alphanumeric()
+        || (text[i - 1].is_lowercase() && text[i].is_uppercase())
+}
+
+/// Scores a folded term against `text` and returns the matched character ranges. A
+/// contiguous occurrence always beats the term's characters scattered in order, which
+/// only counts for terms of three or more characters starting at a word, like `fdflw`
+/// for "Feed flow". Matches at the start of the text or a word, and short texts, score
+/// higher.
+fn fuzzy_match(term: &[char], text: &str) -> Option<(f64, Vec<(usize, usize)>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    if term.is_empty() || term.len() > folded.len() {
+        return None;
+    }
+    let extra = (folded.len() - term.len()) as f64;
+
+    let occurrences: Vec<usize> = (0..=folded.len() - term.len())
+        .filter(|&i| folded[i..i + term.len()] == *term)
+        .collect();
+    let word_start = occurrences.iter().find(|&&i| is_word_start(&chars, i));
+    if let Some(&start) = word_start.or(occurrences.first()) {
+        let mut score = 100.0 - extra.min(40.0) * 0.5;
+        if start == 0 {
+            score += 30.0;
+        } else if is_word_start(&chars, start) {
+            score += 20.0;
+        }
+        if extra == 0.0 {
+            score += 50.0;
+        }
+        return Some((score, vec![(start, start + term.len())]));
+    }
+
+    if term.len() < 3 {
+        return None;
+    }
+    (0..folded.len())
+        .filter(|&i| folded[i] == term[0] && is_word_start(&chars, i))
+        .find_map(|first| {
+            let mut spans = vec![(first, first + 1)];
+            let mut score = 8.0;
+            let mut pos = first + 1;
+            for &c in &term[1..] {
+                let i = pos + folded[pos..].iter().position(|&f| f == c)?;
+                score += if is_word_start(&chars, i) { 8.0 } else { 2.0 };
+                match spans.last_mut() {
+                    Some(last) if last.1 == i => {
+                        last.1 = i + 1;
+                        score += 5.0;
+                    }
+                    _ => spans.push((i, i + 1)),
+                }
+                pos = i + 1;
+            }
+            score -= spans.len() as f64 * 3.0 + extra.min(40.0) * 0.25;
+            Some((score.clamp(1.0, 60.0), spans))
+        })
+}
+
+/// Whether a component path, or the part of it from any segment on, matches `pattern`;
+/// `Boiler*` matches `Plant/Unit2/Boiler/FeedPump`.
+fn component_matches(pattern: &Regex, component: &str) -> bool {
+    pattern.is_match(component)
+        || component
+            .match_indices(['/', '\\'])
+            .any(|(i, _)| pattern.is_match(component[i + 1..].trim()))
+}
+
+/// Ranks the sensors against a query of space-separated terms, best first. Terms such
+/// as `unit:degC` or `component:Boiler*` filter on a field: the value, quoted if it
+/// holds spaces, must match the whole field ignoring case, with `*` and `?` as
+/// wildcards. Every other term must fuzzy-match the tag, description, unit or
+/// component; a query of filters alone lists the matching sensors by name.
+pub fn search_sensors(
+    query: &str,
+    columns: &[SensorColumn],
+    metadata: &MetadataStore,
+) -> Result<Vec<SensorMatch>, String> {
+    let mut filters = Vec::new();
+    let mut terms: Vec<Vec<char>> = Vec::new();
+    for token in tokens(query) {
+        let filter = token
+            .split_once(':')
+            .and_then(|(field, value)| Some((SearchField::of_filter(field)?, value)));
+        match filter {
+            Some((field, value)) => {
+                let pattern = Regex::new(&glob_to_regex(value.trim()))
+                    .map_err(|e| format!("Invalid search filter {}: {}", token, e))?;
+                filters.push(Filter { field, pattern });
+            }
+            None => terms.push(token.chars().map(fold).collect()),
+        }
+    }
+
+    let mut matches = Vec::new();
+    'sensors: for column in columns {
+        let meta = metadata.get(column.tag());
+        let mut unit = meta.map(|m| m.unit.clone()).unwrap_or_default();
+        if unit.is_empty() {
+            unit = column.unit.clone().unwrap_or_default();
+        }
+        let mut sensor_match = SensorMatch {
+            sensor: column.name.clone(),
+            description: meta.map(|m| m.description.clone()).unwrap_or_default(),
+            unit,
+            component: meta.map(|m| m.component.clone()).unwrap_or_default(),
+            score: 0.0,
+            spans: Vec::new(),
+        };
+        let field_text = |field: SearchField| match field {
+            SearchField::Tag => sensor_match.sensor.as_str(),
+            SearchField::Description => sensor_match.description.as_str(),
+            SearchField::Unit => sensor_match.unit.as_str(),
+            SearchField::Component => sensor_match.component.as_str(),
+        };
+
+        for filter in &filters {
+            let text = field_text(filter.field);
+            let matched = match filter.field {
+                SearchField::Component => component_matches(&filter.pattern, text),
+                _ => filter.pattern.is_match(text.trim()),
+            };
+            if !matched {
+                continue 'sensors;
+            }
+        }
+
+        // Each term scores by its best field and highlights every field it matches
+        let mut score = 0.0;
+        let mut spans = Vec::new();
+        for term in &terms {
+            let mut best: Option<f64> = None;
+            for field in SearchField::ALL {
+                let Some((field_score, field_spans)) = fuzzy_match(term, field_text(field)) else {
+                    continue;
+                };
+                let weighted = field_score * field.weight();
+                best = Some(best.map_or(weighted, |b: f64| b.max(weighted)));
+                spans.extend(field_spans.into_iter().map(|(start, end)| MatchSpan {
+                    field,
+                    start,
+                    end,
+                }));
+            }
+            match best {
+                Some(best) => score += best,
+                None => continue 'sensors,
+            }
+        }
+
+        // Merge spans of different terms that overlap
+        spans.sort_by_key(|s| (s.field, s.start));
+        for span in spans {
+            match sensor_match.spans.last_mut() {
+                Some(last) if last.field == span.field && span.start <= last.end => {
+                    last.end = last.end.max(span.end);
+                }
+                _ => sensor_match.spans.push(span),
+            }
+        }
+        sensor_match.score = score;
+        matches.push(sensor_match);
+    }
+
+    matches.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.sensor.to_lowercase().cmp(&b.sensor.to_lowercase()))
+    });
+    Ok(matches)
+}
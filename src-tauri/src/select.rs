@@ -0,0 +1,63 @@
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// One entry of the column include list in `ImportOptions::columns`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ColumnSelector {
+    /// A tag name, compared case-insensitively.
+    Exact { name: String },
+    /// A case-insensitive wildcard pattern where `*` matches any run of characters
+    /// and `?` a single one, e.g. `VIB-*-X`.
+    Glob { pattern: String },
+    /// A regular expression searched for anywhere in the name; anchor it with `^`/`$`
+    /// to match whole names.
+    Regex { pattern: String },
+}
+
+/// Compiled include list. Matches every column when the list is empty.
+pub struct ColumnFilter {
+    exact: HashSet<String>,
+    patterns: RegexSet,
+}
+
+pub fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("(?i)^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+impl ColumnFilter {
+    pub fn new(selectors: &[ColumnSelector]) -> Result<Self, String> {
+        let mut exact = HashSet::new();
+        let mut patterns = Vec::new();
+        for selector in selectors {
+            match selector {
+                ColumnSelector::Exact { name } => {
+                    exact.insert(name.trim().to_lowercase());
+                }
+                ColumnSelector::Glob { pattern } => patterns.push(glob_to_regex(pattern.trim())),
+                ColumnSelector::Regex { pattern } => patterns.push(pattern.clone()),
+            }
+        }
+        let patterns =
+            RegexSet::new(&patterns).map_err(|e| format!("Invalid column pattern: {}", e))?;
+        Ok(ColumnFilter { exact, patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.is_empty() || self.exact.contains(&name.to_lowercase()) || self.patterns.is_match(name)
+    }
+}
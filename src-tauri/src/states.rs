@@ -0,0 +1,83 @@
+use crate::column_store::ColumnType;
+use crate::csv_processor::ImportOptions;
+use std::collections::HashSet;
+
+/// Non-empty values sampled per column when inferring its type.
+const TYPE_SAMPLE_SIZE: usize = 1000;
+/// Most distinct labels a text column may show in the sample to be read as states.
+/// Columns with more are free text and stay numeric, reporting their cells as unparsable.
+pub const MAX_STATES: usize = 64;
+
+/// Parses the usual spellings of a digital value, ignoring case.
+pub fn parse_boolean(value: &str) -> Option<bool> {
+    let value = value.trim();
+    if ["true", "yes", "on"]
+        .iter()
+        .any(|t| value.eq_ignore_ascii_case(t))
+    {
+        Some(true)
+    } else if ["false", "no", "off"]
+        .iter()
+        .any(|f| value.eq_ignore_ascii_case(f))
+    {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Text after the number a cell starts with, read loosely so that numbers in another
+/// locale ("12,5", "1'000.5") still count, or `None` when the cell does not start with one.
+fn after_leading_number(value: &str) -> Option<&str> {
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || ".,'+-\u{a0}\u{202f} ".contains(c)))
+        .unwrap_or(value.len());
+    value[..end]
+        .contains(|c: char| c.is_ascii_digit())
+        .then(|| value[end..].trim())
+}
+
+/// Picks the type of a column from its leading values. A column is numeric as soon
+/// as one value parses as a number, unless the numbers carry different units (labels
+/// like "1 Running" and "2 Stopped"); text-only columns are boolean when every value
+/// is a boolean spelling, and states when they hold at most `MAX_STATES` distinct labels.
+/// Numbers the options cannot parse, such as decimal commas under the default options,
+/// keep the column numeric so that their cells are reported instead of becoming labels.
+/// NaN tokens are skipped like empty cells, so leading "N/A" rows do not decide the type.
+pub fn infer_column_type<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+    options: &ImportOptions,
+) -> ColumnType {
+    let samples: Vec<&str> = values
+        .into_iter()
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && !options.parse_number(v).is_some_and(f64::is_nan))
+        .take(TYPE_SAMPLE_SIZE)
+        .collect();
+    let numbers: Vec<(f64, Option<&str>)> = samples
+        .iter()
+        .filter_map(|v| options.parse_number_unit(v))
+        .collect();
+    let units: HashSet<&str> = numbers.iter().filter_map(|(_, unit)| *unit).collect();
+    if samples.is_empty() || (!numbers.is_empty() && units.len() <= 1) {
+        return ColumnType::Numeric;
+    }
+    if samples.iter().all(|v| parse_boolean(v).is_some()) {
+        return ColumnType::Boolean;
+    }
+    let unparsed: Vec<&str> = samples
+        .iter()
+        .filter_map(|v| after_leading_number(v))
+        .collect();
+    let unparsed_units: HashSet<&str> = unparsed.iter().copied().collect();
+    if unparsed_units.contains("") || (unparsed.len() == samples.len() && unparsed_units.len() == 1)
+    {
+        return ColumnType::Numeric;
+    }
+    let distinct: HashSet<&str> = samples.iter().copied().collect();
+    if distinct.len() <= MAX_STATES {
+        ColumnType::State
+    } else {
+        ColumnType::Numeric
+    }
+}
@@ -0,0 +1,256 @@
+use crate::archive::{self, MEMBER_SEPARATOR};
+use crate::clock::{self, ClockCorrection};
+use crate::column_store::ProcessedData;
+use crate::csv_processor::{
+    configured_encoding, is_named_file, merge_datasets, probe_csv, read_appended_rows, tag_columns,
+    ConflictPolicy, CsvRecord, DataChunk, ImportOptions, IngestReport, TableLayout, WideLayout,
+};
+use encoding_rs::Encoding;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Where following each input resumes: the bytes the load read from it, so rows
+/// written during the load are neither lost nor read twice. Archives, compressed
+/// files and other formats report `None` and are never followed.
+pub fn file_lengths(paths: &[String], report: &IngestReport) -> Vec<Option<u64>> {
+    paths
+        .iter()
+        .map(|path| {
+            if !is_plain_csv(path) {
+                return None;
+            }
+            report.files.iter().find(|f| &f.path == path)?.bytes_read
+        })
+        .collect()
+}
+
+fn is_plain_csv(path: &str) -> bool {
+    if path.contains(MEMBER_SEPARATOR) {
+        return false;
+    }
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    !matches!(
+        extension.as_deref(),
+        Some("parquet" | "xlsx" | "xlsm" | "gz" | "zst" | "zip")
+    )
+}
+
+/// A CSV file being followed: rows past `offset` have not been read yet.
+pub struct Follower {
+    path: String,
+    offset: u64,
+    header_list: Vec<String>,
+    /// Column types, quality pairs and timestamp format of the loaded rows.
+    layout: WideLayout,
+    /// Options with the file's timezone override applied.
+    options: ImportOptions,
+    /// Shift applied to the file's rows at load time, applied again to appended rows.
+    clock: Option<ClockCorrection>,
+    /// Column prefix the file's columns were given at load time.
+    prefix: Option<String>,
+    /// True until the first poll, whose start may fall in the middle of a line.
+    unaligned: bool,
+}
+
+impl Follower {
+    /// Prepares to follow `path`, one of the session's input `paths`, from `offset`.
+    /// Returns `None` for files that cannot
+    /// be followed: long-format tables and encodings that are not ASCII-compatible,
+    /// where a byte offset does not mark a line boundary.
+    pub fn new(
+        path: &str,
+        paths: &[String],
+        offset: u64,
+        options: &ImportOptions,
+        clock: Option<ClockCorrection>,
+    ) -> Result<Option<Self>, String> {
+        let options = &clock::options_for_file(options, clock::file_options(options, path));
+        if matches!(options.layout, TableLayout::Long(_)) {
+            return Ok(None);
+        }
+        let mut head = [0u8; 3];
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let read = file.read(&mut head).map_err(|e| e.to_string())?;
+        let encoding = match configured_encoding(options)? {
+            Some(encoding) => Some(encoding),
+            None => Encoding::for_bom(&head[..read]).map(|(encoding, _)| encoding),
+        };
+        if encoding.is_some_and(|e| !e.is_ascii_compatible()) {
+            return Ok(None);
+        }
+
+        let (header_list, layout) = probe_csv(path, options)?;
+        // Prefixes depend on every input, as in the initial load
+        let inputs = archive::expand_archives(paths)?;
+        let prefix = inputs
+            .iter()
+            .position(|input| input == path)
+            .and_then(|i| options.naming.prefixes(&inputs).swap_remove(i));
+        Ok(Some(Follower {
+            path: path.to_string(),
+            offset,
+            header_list,
+            layout,
+            options: options.clone(),
+            clock,
+            prefix,
+            unaligned: true,
+        }))
+    }
+
+    /// Reads the complete lines appended since the last poll. A trailing partial
+    /// line is left for the next poll.
+    pub fn poll(&mut self) -> Result<Option<ProcessedData>, String> {
+        let len = std::fs::metadata(&self.path)
+            .map_err(|e| e.to_string())?
+            .len();
+        if len < self.offset {
+            // Truncated or replaced; only rows written from here on are followed
+            println!("{} shrank, following from its new end", self.path);
+            self.offset = len;
+            return Ok(None);
+        }
+        if len == self.offset {
+            return Ok(None);
+        }
+
+        // Back up one byte on the first poll to tell whether the offset sits on a line start
+        let start = if self.unaligned {
+            self.offset.saturating_sub(1)
+        } else {
+            self.offset
+        };
+        let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(start))
+            .map_err(|e| e.to_string())?;
+        let mut bytes = Vec::with_capacity((len - start) as usize);
+        file.take(len - start)
+            .read_to_end(&mut bytes)
+            .map_err(|e| e.to_string())?;
+
+        let skip = if self.unaligned && start < self.offset {
+            match bytes.iter().position(|&b| b == b'\n') {
+                Some(newline) => newline + 1,
+                None => return Ok(None),
+            }
+        } else {
+            0
+        };
+        let Some(last_newline) = bytes.iter().rposition(|&b| b == b'\n') else {
+            return Ok(None);
+        };
+        if last_newline < skip {
+            return Ok(None);
+        }
+        self.unaligned = false;
+        self.offset = start + last_newline as u64 + 1;
+
+        bytes.truncate(last_newline + 1);
+        bytes.drain(..skip);
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        let (mut data, _) = read_appended_rows(
+            &self.path,
+            bytes,
+            &self.header_list,
+            &self.layout,
+            &self.options,
+        )?;
+        // Shift and name the rows as the initial load did so they map onto the session
+        if let Some(clock) = &self.clock {
+            for ts in &mut data.timestamps {
+                *ts = clock.apply(*ts);
+            }
+        }
+        tag_columns(&mut data, &self.path, self.prefix.as_deref());
+        Ok(Some(data))
+    }
+}
+
+/// Adds rows appended to `path` to the session and returns them, in the session's
+/// column order, as the chunk to emit. Rows past the end of the timeline are appended
+/// in place; anything else falls back to a full merge that resolves overlaps by the
+/// session's conflict `policy`, as the initial load did.
+pub fn append_rows(
+    data: &mut ProcessedData,
+    path: &str,
+    mut new: ProcessedData,
+    policy: &ConflictPolicy,
+) -> Result<DataChunk, String> {
+    let mapping: Vec<Option<usize>> = data
+        .columns
+        .iter()
+        .map(|column| {
+            new.columns
+                .iter()
+                .position(|c| c.name.eq_ignore_ascii_case(&column.name))
+        })
+        .collect();
+    for (column, idx) in data.columns.iter().zip(&mapping) {
+        if let Some(c) = idx.filter(|c| new.columns[*c].kind_conflicts(column)) {
+            return Err(format!(
+                "Column {} in {} no longer holds {}",
+                new.columns[c].name,
+                path,
+                if column.is_categorical() {
+                    "labels"
+                } else {
+                    "numbers"
+                }
+            ));
+        }
+    }
+    // Appended state labels get the codes the session already uses for them
+    for (column, idx) in data.columns.iter_mut().zip(&mapping) {
+        if let Some(c) = idx {
+            new.columns[*c].recode_to(column);
+        }
+    }
+    let chunk = DataChunk {
+        headers: data.columns.iter().map(|c| c.name.clone()).collect(),
+        rows: (0..new.row_count())
+            .map(|row| CsvRecord::of_row(&new, &mapping, row, false))
+            .collect(),
+    };
+
+    let in_order = new.timestamps.windows(2).all(|w| w[0] < w[1])
+        && match (data.timestamps.last(), new.timestamps.first()) {
+            (Some(last), Some(first)) => last < first,
+            _ => true,
+        };
+    let known_columns = new.columns.iter().all(|c| {
+        data.columns
+            .iter()
+            .any(|column| column.name.eq_ignore_ascii_case(&c.name))
+    });
+
+    if in_order && known_columns {
+        data.timestamps.extend_from_slice(&new.timestamps);
+        for (column, idx) in data.columns.iter_mut().zip(&mapping) {
+            for row in 0..new.row_count() {
+                match idx {
+                    Some(c) => {
+                        let appended = &new.columns[*c];
+                        column.push_sample(appended.get(row), appended.is_bad(row));
+                    }
+                    None => column.push(None),
+                }
+            }
+        }
+    } else {
+        // Earlier files, a preferred one among them, are all in the session
+        let policy = match policy {
+            ConflictPolicy::Prefer { file } if !is_named_file(path, file) => ConflictPolicy::First,
+            policy => policy.clone(),
+        };
+        // Merge a copy so that a failed merge, e.g. under `Fail`, leaves the session intact
+        let sources = ["session".to_string(), path.to_string()];
+        (*data, _) = merge_datasets(vec![data.clone(), new], &sources, &policy)?;
+    }
+    Ok(chunk)
+}
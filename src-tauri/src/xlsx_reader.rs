@@ -0,0 +1,313 @@
+use crate::column_store::{ColumnType, ProcessedData, SensorColumn};
+use crate::csv_processor::{
+    find_timestamp_column, new_file_report, FileReport, ImportOptions, TimestampParser,
+};
+use crate::select::ColumnFilter;
+use crate::states::infer_column_type;
+use calamine::{
+    open_workbook, Data, DataType, ExcelDateTime, ExcelDateTimeType, Range, Reader, Xlsx,
+};
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::time::Instant;
+use zip::ZipArchive;
+
+/// Number of leading data rows inspected when looking for a date-typed column.
+const DETECT_ROWS: usize = 50;
+
+pub fn list_sheets(path: &str) -> Result<Vec<String>, String> {
+    let workbook = open_workbook::<Xlsx<_>, _>(path).map_err(|e| e.to_string())?;
+    Ok(workbook.sheet_names())
+}
+
+/// Zero-based absolute (row, column) position of a cell.
+type CellRef = (u32, u32);
+
+/// Parses an A1-style range such as "B2:F500" into zero-based absolute
+/// (row, column) corners. A single cell reference selects from there to the end.
+fn parse_cell_range(range: &str) -> Result<(CellRef, Option<CellRef>), String> {
+    let mut parts = range.trim().split(':');
+    let start = parse_cell_ref(parts.next().unwrap_or(""))
+        .ok_or_else(|| format!("Invalid cell range: {}", range))?;
+    let end = match parts.next() {
+        Some(end) => {
+            Some(parse_cell_ref(end).ok_or_else(|| format!("Invalid cell range: {}", range))?)
+        }
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err(format!("Invalid cell range: {}", range));
+    }
+    Ok((start, end))
+}
+
+fn parse_cell_ref(cell: &str) -> Option<CellRef> {
+    let cell = cell.trim().replace('$', "").to_ascii_uppercase();
+    let split = cell.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty() || !letters.bytes().all(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    let col = letters.bytes().try_fold(0u32, |acc, b| {
+        acc.checked_mul(26)?.checked_add((b - b'A' + 1) as u32)
+    })?;
+    let row: u32 = digits.parse().ok()?;
+    if row == 0 {
+        return None;
+    }
+    Some((row - 1, col - 1))
+}
+
+/// Whether the workbook counts serial dates from 1904, as older Mac workbooks do.
+/// calamine applies the setting to date-formatted cells only.
+fn uses_1904_dates(path: &str) -> Result<bool, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut archive =
+        ZipArchive::new(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?;
+    let mut workbook = String::new();
+    archive
+        .by_name("xl/workbook.xml")
+        .map_err(|e| format!("{}: {}", path, e))?
+        .read_to_string(&mut workbook)
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let Some(start) = workbook.find("<workbookPr") else {
+        return Ok(false);
+    };
+    let properties = &workbook[start..];
+    let properties = &properties[..properties.find('>').unwrap_or(properties.len())];
+    Ok([
+        "date1904=\"1\"",
+        "date1904=\"true\"",
+        "date1904='1'",
+        "date1904='true'",
+    ]
+    .iter()
+    .any(|flag| properties.contains(flag)))
+}
+
+/// Converts a cell of the timestamp column. Numbers are Excel serial dates in the
+/// workbook's date system.
+fn cell_timestamp(cell: &Data, parser: &TimestampParser, is_1904: bool) -> Option<DateTime<Utc>> {
+    let serial = |v: f64| ExcelDateTime::new(v, ExcelDateTimeType::DateTime, is_1904);
+    match cell {
+        Data::DateTime(dt) => parser.localize(dt.as_datetime()?),
+        Data::Float(v) => parser.localize(serial(*v).as_datetime()?),
+        Data::Int(v) => parser.localize(serial(*v as f64).as_datetime()?),
+        Data::DateTimeIso(s) | Data::String(s) => parser.parse(s),
+        _ => None,
+    }
+}
+
+/// The value of a numeric cell, and the unit after the number of a text cell such
+/// as "12.5 °C".
+fn cell_value<'a>(cell: &'a Data, options: &ImportOptions) -> Option<(f64, Option<&'a str>)> {
+    match cell {
+        Data::Float(v) => Some((*v, None)),
+        Data::Int(v) => Some((*v as f64, None)),
+        Data::Bool(b) => Some((if *b { 1.0 } else { 0.0 }, None)),
+        Data::DateTime(dt) => Some((dt.as_f64(), None)),
+        Data::String(s) => options.parse_number_unit(s),
+        _ => None,
+    }
+}
+
+/// Error cells such as `#N/A` and configured bad-value tokens.
+fn is_bad_cell(cell: &Data, options: &ImportOptions) -> bool {
+    match cell {
+        Data::Error(_) => true,
+        Data::String(s) => options.quality.is_bad_value(s),
+        _ => false,
+    }
+}
+
+/// Boolean for columns of boolean cells; text columns are typed by their contents.
+fn infer_cell_type<'a>(
+    cells: impl Iterator<Item = &'a Data> + Clone,
+    options: &ImportOptions,
+) -> ColumnType {
+    let cells = cells.filter(|cell| !cell.is_empty() && !is_bad_cell(cell, options));
+    if cells.clone().next().is_none() {
+        return ColumnType::Numeric;
+    }
+    if cells.clone().all(|cell| matches!(cell, Data::Bool(_))) {
+        return ColumnType::Boolean;
+    }
+    if cells.clone().all(|cell| matches!(cell, Data::String(_))) {
+        return infer_column_type(cells.filter_map(|cell| cell.get_string()), options);
+    }
+    ColumnType::Numeric
+}
+
+fn cell_state(cell: &Data, column: &mut SensorColumn) -> Option<f64> {
+    match (column.column_type, cell) {
+        (_, Data::Empty) => None,
+        (ColumnType::Boolean, Data::Bool(b)) => Some(if *b { 1.0 } else { 0.0 }),
+        (ColumnType::Boolean, Data::String(s)) => column.boolean_code(s),
+        (ColumnType::Boolean, _) => None,
+        (_, cell) => Some(column.state_code(cell.to_string().trim())),
+    }
+}
+
+/// Reads one sheet (the first when `options.sheet` is unset), optionally limited to
+/// `options.cell_range`. The first row of the range, after `header_row` rows, is the header.
+pub fn read_xlsx(
+    path: &str,
+    options: &ImportOptions,
+) -> Result<(ProcessedData, FileReport), String> {
+    let total_start = Instant::now();
+    let mut workbook = open_workbook::<Xlsx<_>, _>(path).map_err(|e| e.to_string())?;
+    let is_1904 = uses_1904_dates(path)?;
+    let sheet = match &options.sheet {
+        Some(sheet) => sheet.clone(),
+        None => workbook
+            .sheet_names()
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("{}: workbook has no sheets", path))?,
+    };
+    let mut range: Range<Data> = workbook
+        .worksheet_range(&sheet)
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    if let Some(cell_range) = &options.cell_range {
+        let (start, end) = parse_cell_range(cell_range)?;
+        let end = end.or(range.end()).unwrap_or(start);
+        range = range.range(start, end);
+    }
+    // Absolute sheet row of the first range row, for 1-based row numbers in the report
+    let first_row = range.start().map_or(0, |(row, _)| row) as u64;
+
+    let mut rows = range.rows().enumerate().skip(options.header_row);
+    let header_list: Vec<String> = match rows.next() {
+        Some((_, header)) => header
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| match cell {
+                Data::Empty => format!("Column{}", i + 1),
+                other => other.to_string().trim().to_string(),
+            })
+            .collect(),
+        None => return Err(format!("{}: sheet {} is empty", path, sheet)),
+    };
+    let data_rows: Vec<(usize, &[Data])> = rows.skip(options.skip_lines).collect();
+
+    // A header named like a timestamp wins, otherwise the first date-typed column
+    let timestamp_idx = find_timestamp_column(&header_list, &options.timestamp)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .or_else(|| {
+            (0..header_list.len()).find(|&c| {
+                data_rows
+                    .iter()
+                    .take(DETECT_ROWS)
+                    .filter_map(|(_, row)| row.get(c))
+                    .find(|cell| !cell.is_empty())
+                    .is_some_and(|cell| matches!(cell, Data::DateTime(_) | Data::DateTimeIso(_)))
+            })
+        })
+        .ok_or_else(|| format!("{}: no timestamp column found in sheet {}", path, sheet))?;
+
+    let ts_parser = TimestampParser::new(&options.timestamp)?.detect(
+        data_rows
+            .iter()
+            .filter_map(|(_, row)| match row.get(timestamp_idx) {
+                Some(Data::String(s)) | Some(Data::DateTimeIso(s)) => Some(s.as_str()),
+                _ => None,
+            }),
+        options.timestamp.day_first,
+    );
+
+    let filter = ColumnFilter::new(&options.columns)?;
+    let quality_columns = options
+        .quality
+        .pair_columns(&header_list, |i| {
+            data_rows
+                .iter()
+                .filter_map(move |(_, row)| row.get(i))
+                .map(|cell| cell.to_string())
+        })
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let value_indices: Vec<usize> = (0..header_list.len())
+        .filter(|&i| {
+            i != timestamp_idx && !quality_columns.is_quality[i] && filter.matches(&header_list[i])
+        })
+        .collect();
+
+    let mut report = new_file_report(path, &header_list);
+    let mut timestamps: Vec<DateTime<Utc>> = Vec::with_capacity(data_rows.len());
+    let mut columns: Vec<SensorColumn> = value_indices
+        .iter()
+        .map(|&i| {
+            let cells = data_rows.iter().filter_map(move |(_, row)| row.get(i));
+            SensorColumn::with_capacity(header_list[i].clone(), data_rows.len())
+                .with_type(infer_cell_type(cells, options))
+        })
+        .collect();
+
+    for (row_idx, row) in data_rows {
+        let line = first_row + row_idx as u64 + 1;
+        report.total_rows += 1;
+
+        let ts_cell = row.get(timestamp_idx).unwrap_or(&Data::Empty);
+        let Some(ts) = cell_timestamp(ts_cell, &ts_parser, is_1904) else {
+            if ts_cell.is_empty() {
+                report.columns[timestamp_idx].empty_cells += 1;
+            } else {
+                report.record_bad_cell(timestamp_idx, line, ts_cell.to_string().as_bytes());
+            }
+            report.missing_timestamp_rows += 1;
+            continue;
+        };
+        timestamps.push(ts);
+
+        for (col, &i) in value_indices.iter().enumerate() {
+            let cell = row.get(i).unwrap_or(&Data::Empty);
+            if is_bad_cell(cell, options) {
+                report.columns[i].bad_quality_cells += 1;
+                columns[col].push_sample(None, true);
+                continue;
+            }
+            let value = if columns[col].is_categorical() {
+                cell_state(cell, &mut columns[col])
+            } else {
+                let parsed = cell_value(cell, options);
+                if let Some((_, Some(unit))) = parsed {
+                    report.record_unit(i, unit);
+                }
+                parsed.map(|(value, _)| value)
+            };
+            if cell.is_empty() {
+                report.columns[i].empty_cells += 1;
+            } else if value.is_none() {
+                report.record_bad_cell(i, line, cell.to_string().as_bytes());
+            }
+            let good = quality_columns.of[i]
+                .and_then(|q| row.get(q))
+                .is_none_or(|q| options.quality.is_good(&q.to_string()));
+            let bad = !good && !cell.is_empty();
+            if bad {
+                report.columns[i].bad_quality_cells += 1;
+            }
+            columns[col].push_sample(value, bad);
+        }
+    }
+    for (column, &i) in columns.iter_mut().zip(&value_indices) {
+        column.unit = report.columns[i].unit.clone();
+    }
+
+    println!(
+        "Read {} rows from sheet {}, read_xlsx took: {:?}",
+        timestamps.len(),
+        sheet,
+        total_start.elapsed()
+    );
+
+    Ok((
+        ProcessedData {
+            timestamp_header: header_list[timestamp_idx].clone(),
+            timestamps,
+            columns,
+        },
+        report,
+    ))
+}
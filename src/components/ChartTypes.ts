@@ -1,7 +1,8 @@
-import { CsvRecord } from '../types';
+import { ColumnStates, CsvRecord } from '../types';
 
 export interface ChartProps {
     data: CsvRecord[];
     sensors: string[];
     headers: string[];
+    states?: ColumnStates[];
 }
@@ -1,10 +1,10 @@
 import { useState, useMemo, useEffect, useDeferredValue, useRef } from 'react';
 import { invoke } from "@tauri-apps/api/core";
 import { listen, emit, UnlistenFn } from "@tauri-apps/api/event";
-import { ProcessedData, CsvMetadata, SensorMetadata, CsvRecord, SensorOperationConfig } from '../types';
+import { ProcessedData, CsvMetadata, SensorMetadata, CsvRecord, SensorOperationConfig, ColumnStates } from '../types';
 import DataTable from './DataTable';
 import Chart from './Chart';
-import FilterPanel, { ValueFilter } from './FilterPanel';
+import FilterPanel, { LogicBlock, ValueFilter } from './FilterPanel';
 import SensorSelection from './SensorSelection';
 
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
@@ -56,6 +56,13 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
     const [chartData, setChartData] = useState<ProcessedData | null>(null);
     const [loading, setLoading] = useState(false);
     const [valueFilters, setValueFilters] = useState<ValueFilter[]>([]);
+    const [logicBlocks, setLogicBlocks] = useState<LogicBlock[]>([]);
+    // Drop samples flagged bad by their quality column instead of plotting them
+    const [excludeBad, setExcludeBad] = useState(false);
+    const excludeBadRef = useRef(excludeBad);
+    useEffect(() => {
+        excludeBadRef.current = excludeBad;
+    }, [excludeBad]);
 
     // Sync visibleSensors with selectedSensors when selectedSensors changes
     useEffect(() => {
@@ -87,6 +94,7 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
 
     // Fetch data when sensors change
     useEffect(() => {
+        let unlistenStates: UnlistenFn | undefined;
         let unlistenChunk: UnlistenFn | undefined;
         let unlistenEnd: UnlistenFn | undefined;
 
@@ -102,9 +110,13 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
             setLoading(true);
             const accumRows: CsvRecord[] = [];
             let headers: string[] = [];
+            let states: ColumnStates[] = [];
 
             try {
                 // Setup listeners BEFORE invoking
+                unlistenStates = await listen<ColumnStates[]>('data-stream-states', (event) => {
+                    states = event.payload;
+                });
                 unlistenChunk = await listen<ProcessedData>('data-stream-chunk', (event) => {
                     const chunk = event.payload;
                     if (headers.length === 0) {
@@ -123,14 +135,15 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                 unlistenEnd = await listen('data-stream-end', () => {
                     setChartData({
                         headers: headers.length > 0 ? headers : deferredSensors,
-                        rows: accumRows
+                        rows: accumRows,
+                        states
                     });
                     setLoading(false);
                 });
 
                 console.time("invoke_get_data_stream");
                 // invoke now just starts the process
-                await invoke("get_data", { sensors: deferredSensors });
+                await invoke("get_data", { sensors: deferredSensors, excludeBad });
                 console.timeEnd("invoke_get_data_stream");
 
             } catch (err) {
@@ -142,10 +155,64 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
         fetchData();
 
         return () => {
+            if (unlistenStates) unlistenStates();
             if (unlistenChunk) unlistenChunk();
             if (unlistenEnd) unlistenEnd();
         };
-    }, [deferredSensors]);
+    }, [deferredSensors, excludeBad]);
+
+    const [following, setFollowing] = useState(false);
+
+    const toggleFollow = async () => {
+        try {
+            if (following) {
+                await invoke("stop_following");
+                setFollowing(false);
+            } else {
+                const paths = await invoke<string[]>("follow_files", {});
+                if (paths.length === 0) {
+                    alert("None of the loaded files can be followed (plain wide-format CSV only).");
+                    return;
+                }
+                setFollowing(true);
+            }
+        } catch (e) {
+            console.error("Follow failed:", e);
+        }
+    };
+
+    // Rows appended to followed files arrive with every sensor; keep the selected ones
+    useEffect(() => {
+        let unlistenAppend: UnlistenFn | undefined;
+        listen<ProcessedData>('data-stream-append', (event) => {
+            const chunk = event.payload;
+            setChartData(prev => {
+                if (!prev || prev.headers.length === 0) return prev;
+                const indices = prev.headers.map(h => chunk.headers.indexOf(h));
+                const rows = chunk.rows.map(row => {
+                    const bad = new Set(row.bad ?? []);
+                    const values = indices.map(i => {
+                        if (i < 0 || (excludeBadRef.current && bad.has(i))) return null;
+                        return row.values[i];
+                    });
+                    const flagged = indices.flatMap((i, pos) => (i >= 0 && bad.has(i) ? [pos] : []));
+                    return { timestamp: row.timestamp, values, bad: flagged };
+                });
+                const time = (ts: string | null | undefined) => (ts ? new Date(ts).getTime() : -Infinity);
+                const merged = [...prev.rows, ...rows];
+                // Late rows from a backfilled file are rare; only re-sort when one arrived out of order
+                const outOfOrder = merged.some((r, i) => i >= prev.rows.length && i > 0 && time(r.timestamp) < time(merged[i - 1].timestamp));
+                if (outOfOrder) {
+                    merged.sort((a, b) => time(a.timestamp) - time(b.timestamp));
+                }
+                return { ...prev, rows: merged };
+            });
+        }).then(fn => { unlistenAppend = fn; });
+
+        return () => {
+            if (unlistenAppend) unlistenAppend();
+        };
+    }, []);
 
     // Event handling for Add Sensor Window communication
     // Use ref to keep track of latest state without re-binding listeners
@@ -260,6 +327,36 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
             });
         }
 
+        // 1b. Value filters: a row must pass every logic block; conditions within a block
+        // combine left to right by their AND/OR connector. Filters on sensors that are
+        // not in the fetched data are ignored.
+        const activeFilters = valueFilters.filter(f => chartData.headers.includes(f.sensor));
+        if (activeFilters.length > 0) {
+            const passes = (f: ValueFilter, r: CsvRecord) => {
+                const v = r.values[chartData.headers.indexOf(f.sensor)];
+                if (v === null || v === undefined) return false;
+                switch (f.operation) {
+                    case 'less_than': return f.value1 !== null && v < f.value1;
+                    case 'greater_than': return f.value1 !== null && v > f.value1;
+                    case 'between': return f.value1 !== null && f.value2 !== null && v >= f.value1 && v <= f.value2;
+                    case 'equals': {
+                        // States compare by their code in the sensor's state table
+                        const table = chartData.states?.find(s => s.column === f.sensor);
+                        if (!f.state || !table) return f.value1 !== null && v === f.value1;
+                        return v === table.states.findIndex(l => l.toLowerCase() === f.state!.toLowerCase());
+                    }
+                }
+            };
+            const blocks = new Map<string, ValueFilter[]>();
+            activeFilters.forEach(f => blocks.set(f.block, [...(blocks.get(f.block) ?? []), f]));
+            rows = rows.filter(r => [...blocks.values()].every(conditions =>
+                conditions.reduce<boolean>((acc, f, i) => {
+                    if (i === 0) return passes(f, r);
+                    return f.connector === 'OR' ? acc || passes(f, r) : acc && passes(f, r);
+                }, true)
+            ));
+        }
+
         // 2. Aggregation / Sampling
         // First, apply Operation Logic if exists (Transform values before sampling or after? 
         // Better to transform raw rows first if we want accurate aggregation, 
@@ -288,10 +385,16 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
 
         if (operationConfig) {
             if (operationConfig.mode === 'single' && operationConfig.singleOp) {
-                const { type, value } = operationConfig.singleOp;
+                const { type, value, state } = operationConfig.singleOp;
+                // 'equals' with a state label compares against that label's code in each sensor's state table
+                const targets = chartData.headers.map(h => {
+                    const table = chartData.states?.find(s => s.column === h);
+                    if (type !== 'equals' || !state || !table) return value;
+                    return table.states.findIndex(l => l.toLowerCase() === state.toLowerCase());
+                });
                 processedRows = rows.map(r => ({
                     ...r,
-                    values: r.values.map(v => {
+                    values: r.values.map((v, i) => {
                         if (v === null) return null;
                         switch (type) {
                             case 'add': return v + value;
@@ -299,6 +402,7 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                             case 'multiply': return v * value;
                             case 'divide': return value !== 0 ? v / value : v;
                             case 'power': return Math.pow(v, value);
+                            case 'equals': return v === targets[i] ? 1 : 0;
                             default: return v;
                         }
                     })
@@ -363,7 +467,8 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                                 break;
                         }
                     }
-                    return { ...r, values: [result] };
+                    // The combined value is bad if any input is
+                    return { ...r, values: [result], bad: r.bad?.length ? [0] : undefined };
                 });
             }
         }
@@ -429,7 +534,7 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
         aggregated.sort((a, b) => new Date(a.timestamp!).getTime() - new Date(b.timestamp!).getTime());
 
         return aggregated;
-    }, [chartData, dateRange, samplingMethod, operationConfig]);
+    }, [chartData, dateRange, valueFilters, samplingMethod, operationConfig]);
 
     // Filter data values by visible sensors
     const visibleFilteredData = useMemo(() => {
@@ -446,7 +551,8 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
         // Map rows to only include visible sensor values
         return filteredData.map(row => ({
             ...row,
-            values: visibleIndices.map(i => row.values[i])
+            values: visibleIndices.map(i => row.values[i]),
+            bad: row.bad && visibleIndices.flatMap((i, pos) => (row.bad!.includes(i) ? [pos] : []))
         }));
     }, [filteredData, chartData, visibleSensors, operationConfig]);
 
@@ -520,6 +626,12 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                                         <button className={`chart-type-btn ${chartType === 'scatter' ? 'active' : ''}`} onClick={() => setChartType('scatter')}>Scatter</button>
                                         <button className={`chart-type-btn ${chartType === 'pair' ? 'active' : ''}`} onClick={() => setChartType('pair')}>Pair Plot</button>
                                     </div>
+                                    <button className={`chart-type-btn ${following ? 'active' : ''}`} onClick={toggleFollow} title="Stream rows appended to the loaded files">
+                                        {following ? 'Following' : 'Follow'}
+                                    </button>
+                                    <button className={`chart-type-btn ${excludeBad ? 'active' : ''}`} onClick={() => setExcludeBad(v => !v)} title="Hide samples flagged bad by their quality column">
+                                        {excludeBad ? 'Bad Hidden' : 'Hide Bad'}
+                                    </button>
                                     <button className="chart-type-btn chart-type-btn-accent" onClick={handleAnalysis}>Run Python Analysis</button>
                                     <button className="collapse-btn" onClick={() => togglePanel('chart')} title="Hide panel">
                                         <EyeOff size={14} />
@@ -532,6 +644,7 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                                         data={visibleFilteredData}
                                         sensors={displayHeaders}
                                         headers={displayHeaders}
+                                        states={chartData.states}
                                         chartType={chartType}
                                     />
                                 )}
@@ -756,8 +869,10 @@ export default function Dashboard({ metadata, sensorMetadata, onBack }: Dashboar
                                 <FilterPanel
                                     onBack={onBack}
                                     selectedSensors={selectedSensors}
-                                    valueFilters={valueFilters}
+                                    logicBlocks={logicBlocks}
+                                    onLogicBlocksChange={setLogicBlocks}
                                     onValueFiltersChange={setValueFilters}
+                                    states={chartData?.states}
                                 />
                             </div>
                         </div>
@@ -1,5 +1,5 @@
-import { useState } from 'react';
 import { Plus, X, GripVertical, Copy } from 'lucide-react';
+import { ColumnStates } from '../types';
 
 export interface LogicCondition {
     id: string;
@@ -15,29 +15,68 @@ export interface LogicBlock {
     conditions: LogicCondition[];
 }
 
+/** A complete logic block condition, as applied to the dashboard data. */
 export interface ValueFilter {
     id: string;
+    /** Logic block the condition belongs to; rows must pass every block. */
+    block: string;
+    /** How the condition combines with the ones before it in its block. */
+    connector: 'IF' | 'AND' | 'OR';
     sensor: string;
-    operation: 'less_than' | 'greater_than' | 'between';
+    operation: 'less_than' | 'greater_than' | 'between' | 'equals';
     value1: number | null;
     value2: number | null;
+    /** State label matched by `equals` on boolean and state sensors. */
+    state?: string | null;
 }
 
 interface FilterPanelProps {
     onBack: () => void;
     selectedSensors?: string[];
-    valueFilters?: ValueFilter[];
+    /** Logic blocks being edited; owned by the dashboard so they outlive a collapsed panel. */
+    logicBlocks?: LogicBlock[];
+    onLogicBlocksChange?: (blocks: LogicBlock[]) => void;
     onValueFiltersChange?: (filters: ValueFilter[]) => void;
+    states?: ColumnStates[];
 }
 
 export default function FilterPanel({
     onBack,
     selectedSensors = [],
-    valueFilters = [],
-    onValueFiltersChange
+    logicBlocks = [],
+    onLogicBlocksChange,
+    onValueFiltersChange,
+    states = []
 }: FilterPanelProps) {
 
-    const [logicBlocks, setLogicBlocks] = useState<LogicBlock[]>([]);
+    const stateTable = (sensor: string) => states.find(s => s.column === sensor)?.states;
+
+    // Conditions with a sensor and the values their operation needs become value filters
+    const toValueFilters = (blocks: LogicBlock[]): ValueFilter[] => blocks.flatMap(block =>
+        block.conditions.flatMap((c): ValueFilter[] => {
+            const value1 = c.value1 === '' ? null : Number(c.value1);
+            const value2 = c.value2 === '' ? null : Number(c.value2);
+            const state = stateTable(c.sensor) && c.operation === 'equals' ? c.value1 || null : null;
+            const complete = c.sensor !== '' && (state !== null || (value1 !== null && !isNaN(value1)))
+                && (c.operation !== 'between' || (value2 !== null && !isNaN(value2)));
+            if (!complete) return [];
+            return [{
+                id: c.id,
+                block: block.id,
+                connector: c.connector === 'OR' ? 'OR' : c.connector === 'AND' ? 'AND' : 'IF',
+                sensor: c.sensor,
+                operation: c.operation,
+                value1: state === null ? value1 : null,
+                value2,
+                state
+            }];
+        })
+    );
+
+    const setLogicBlocks = (blocks: LogicBlock[]) => {
+        onLogicBlocksChange?.(blocks);
+        onValueFiltersChange?.(toValueFilters(blocks));
+    };
 
     const operationLabels: Record<string, string> = {
         less_than: 'IS LESS THAN',
@@ -272,23 +311,46 @@ export default function FilterPanel({
                                         ))}
                                     </select>
 
-                                    {/* Value 1 */}
-                                    <input
-                                        type="number"
-                                        value={condition.value1}
-                                        onChange={(e) => updateCondition(block.id, condition.id, 'value1', e.target.value)}
-                                        placeholder="Value"
-                                        style={{
-                                            width: '60px',
-                                            padding: '0.3rem 0.5rem',
-                                            background: 'var(--input-bg)',
-                                            border: '1px solid var(--border)',
-                                            borderRadius: '4px',
-                                            color: 'var(--text-primary)',
-                                            fontSize: '0.75rem',
-                                            outline: 'none'
-                                        }}
-                                    />
+                                    {/* Value 1: a state of boolean/state sensors, a number otherwise */}
+                                    {stateTable(condition.sensor) ? (
+                                        <select
+                                            value={condition.value1}
+                                            onChange={(e) => updateCondition(block.id, condition.id, 'value1', e.target.value)}
+                                            style={{
+                                                padding: '0.3rem 0.5rem',
+                                                background: 'var(--input-bg)',
+                                                border: '1px solid var(--border)',
+                                                borderRadius: '4px',
+                                                color: 'var(--text-primary)',
+                                                fontSize: '0.75rem',
+                                                outline: 'none'
+                                            }}
+                                        >
+                                            <option value="">State</option>
+                                            {stateTable(condition.sensor)!.map(state => (
+                                                <option key={state} value={state} style={{ background: 'var(--bg-secondary)' }}>
+                                                    {state}
+                                                </option>
+                                            ))}
+                                        </select>
+                                    ) : (
+                                        <input
+                                            type="number"
+                                            value={condition.value1}
+                                            onChange={(e) => updateCondition(block.id, condition.id, 'value1', e.target.value)}
+                                            placeholder="Value"
+                                            style={{
+                                                width: '60px',
+                                                padding: '0.3rem 0.5rem',
+                                                background: 'var(--input-bg)',
+                                                border: '1px solid var(--border)',
+                                                borderRadius: '4px',
+                                                color: 'var(--text-primary)',
+                                                fontSize: '0.75rem',
+                                                outline: 'none'
+                                            }}
+                                        />
+                                    )}
 
                                     {/* Operation Select */}
                                     <select
@@ -1,7 +1,7 @@
 import { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { open as openDialog, ask } from "@tauri-apps/plugin-dialog";
-import { CsvMetadata, SensorMetadata } from "../types";
+import { CsvMetadata, MetadataValidation, SensorMetadata } from "../types";
 import { FileText, ArrowRight, X, Upload, File } from "lucide-react";
 
 interface ImportScreenProps {
@@ -26,7 +26,7 @@ export default function ImportScreen({ onDataReady }: ImportScreenProps) {
         try {
             const selected = await openDialog({
                 multiple: true,
-                filters: [{ name: 'CSV', extensions: ['csv'] }]
+                filters: [{ name: 'Sensor data', extensions: ['csv', 'gz', 'zst', 'zip', 'parquet', 'xlsx', 'xlsm'] }]
             });
             if (selected) {
                 let newFiles: string[] = [];
@@ -81,14 +81,10 @@ export default function ImportScreen({ onDataReady }: ImportScreenProps) {
 
                 // Validation: Check consistency between Data and Metadata
                 if (sensorMetadata && dataMetadata) {
-                    const dataHeaders = dataMetadata.headers.filter(h => !['time', 'timestamp'].includes(h.toLowerCase()));
-                    const metaTags = new Set(sensorMetadata.map(m => m.tag.toLowerCase()));
-
-                    const missingInMeta = dataHeaders.filter(h => !metaTags.has(h.toLowerCase()));
-                    const headerSet = new Set(dataHeaders.map(h => h.toLowerCase()));
-                    const missingInData = sensorMetadata.filter(m => !headerSet.has(m.tag.toLowerCase())).map(m => m.tag);
+                    const validation = await invoke<MetadataValidation>("validate_metadata");
+                    const { missing_metadata: missingInMeta, missing_data: missingInData, duplicate_tags: duplicates, out_of_range: outOfRange } = validation;
 
-                    if (missingInMeta.length > 0 || missingInData.length > 0) {
+                    if (missingInMeta.length > 0 || missingInData.length > 0 || duplicates.length > 0 || outOfRange.length > 0) {
                         let msg = "Validation Warning:\n";
                         if (missingInMeta.length > 0) {
                             msg += `\nSensors in Data but missing in Metadata (${missingInMeta.length}):\n${missingInMeta.slice(0, 5).join(", ")}${missingInMeta.length > 5 ? "..." : ""}`;
@@ -96,6 +92,13 @@ export default function ImportScreen({ onDataReady }: ImportScreenProps) {
                         if (missingInData.length > 0) {
                             msg += `\n\nSensors in Metadata but missing in Data (${missingInData.length}):\n${missingInData.slice(0, 5).join(", ")}${missingInData.length > 5 ? "..." : ""}`;
                         }
+                        if (duplicates.length > 0) {
+                            msg += `\n\nTags listed more than once in Metadata (${duplicates.length}):\n${duplicates.slice(0, 5).join(", ")}${duplicates.length > 5 ? "..." : ""}`;
+                        }
+                        if (outOfRange.length > 0) {
+                            const described = outOfRange.slice(0, 5).map(v => `${v.sensor} (${v.below_low} below, ${v.above_high} above)`);
+                            msg += `\n\nSensors with samples outside their Metadata limits (${outOfRange.length}):\n${described.join(", ")}${outOfRange.length > 5 ? "..." : ""}`;
+                        }
                         msg += "\n\nDo you want to proceed?";
 
                         const confirmed = await ask(msg, {
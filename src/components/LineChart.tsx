@@ -4,8 +4,14 @@ import { ChartProps } from './ChartTypes';
 
 const colors = ["#3b82f6", "#10b981", "#6366f1", "#8b5cf6", "#f43f5e", "#f59e0b"];
 
-function LineChart({ data, sensors, headers }: ChartProps) {
+function LineChart({ data, sensors, headers, states }: ChartProps) {
     const option = useMemo(() => {
+        // Boolean and state sensors carry codes into their state table.
+        const labels = new Map((states ?? []).map(s => [s.column, s.states]));
+        const label = (sensor: string, value: any) => {
+            const table = labels.get(sensor);
+            return table && typeof value === 'number' ? table[value] ?? value : value;
+        };
         const dataCount = data.length;
         const isLargeData = dataCount > 10000;
 
@@ -24,9 +30,11 @@ function LineChart({ data, sensors, headers }: ChartProps) {
                     let content = `<div style="font-weight:bold; margin-bottom:5px;">${dateStr}</div>`;
                     const maxItems = 10;
                     pList.slice(0, maxItems).forEach((p: any) => {
+                        // Samples flagged bad by their quality column are marked in the tooltip
+                        const bad = data[p.dataIndex]?.bad?.includes(headers.indexOf(p.seriesName));
                         content += `<div style="display:flex; align-items:center; gap:5px;">
                             <span style="display:inline-block;width:10px;height:10px;border-radius:50%;background-color:${p.color};"></span>
-                            <span>${p.seriesName}: ${p.value}</span>
+                            <span>${p.seriesName}: ${label(p.seriesName, p.value)}${bad ? ' (bad)' : ''}</span>
                         </div>`;
                     });
                     return content;
@@ -48,13 +56,15 @@ function LineChart({ data, sensors, headers }: ChartProps) {
             },
             yAxis: sensors.map((sensor, index) => {
                 const color = colors[index % colors.length];
+                const table = labels.get(sensor);
                 return {
                     type: 'value',
                     name: sensor,
+                    ...(table ? { min: 0, max: Math.max(table.length - 1, 1), interval: 1 } : {}),
                     position: index % 2 === 0 ? 'left' : 'right',
                     offset: Math.floor(index / 2) * 60,
                     axisLine: { show: true, lineStyle: { color: color } },
-                    axisLabel: { color: color },
+                    axisLabel: { color: color, formatter: (val: number) => label(sensor, val) },
                     splitLine: { show: index === 0, lineStyle: { color: '#334155', type: 'dashed', opacity: 0.3 } }
                 };
             }),
@@ -66,14 +76,15 @@ function LineChart({ data, sensors, headers }: ChartProps) {
                     type: 'line',
                     yAxisIndex: index,
                     data: data.map(d => d.values[sensorIdx] ?? null),
-                    smooth: !isLargeData,
+                    smooth: !isLargeData && !labels.has(sensor),
+                    step: labels.has(sensor) ? 'end' : undefined,
                     showSymbol: false,
                     itemStyle: { color: color },
                     lineStyle: { width: isLargeData ? 1 : 2 }
                 };
             })
         };
-    }, [data, sensors, headers]);
+    }, [data, sensors, headers, states]);
 
     return (
         <ReactECharts option={option} style={{ height: '100%', width: '100%', minHeight: '300px' }} notMerge={true} theme="dark" />
@@ -38,7 +38,7 @@ export default function SensorExplorer({
         const noComponent: string[] = [];
 
         sensors.forEach(sensor => {
-            const meta = sensorMetadata?.find(m => m.tag === sensor);
+            const meta = sensorMetadata?.find(m => m.tag.trim().toLowerCase() === sensor.trim().toLowerCase());
             if (meta && meta.component) {
                 if (!groups[meta.component]) {
                     groups[meta.component] = [];
@@ -58,7 +58,7 @@ export default function SensorExplorer({
     // Helper to render a sensor item
     const renderSensorItem = (sensor: string) => {
         const isSelected = selectedSensors.includes(sensor);
-        const meta = sensorMetadata?.find(m => m.tag === sensor);
+        const meta = sensorMetadata?.find(m => m.tag.trim().toLowerCase() === sensor.trim().toLowerCase());
 
         return (
             <div
@@ -229,6 +229,7 @@ export default function SensorSelection({
                                         return (
                                             <span style={{ fontSize: '0.75rem', color: 'var(--text-secondary)' }}>
                                                 {meta.tag} • {meta.unit}
+                                                {meta.low_limit !== null && meta.high_limit !== null && ` • ${meta.low_limit} – ${meta.high_limit}`}
                                             </span>
                                         );
                                     }
@@ -15,12 +15,16 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
     // Single Op State
     const [singleOpType, setSingleOpType] = useState<SingleOperationType>('add');
     const [singleOpValue, setSingleOpValue] = useState<number>(0);
+    // State label compared by 'equals' on boolean/state sensors
+    const [singleOpState, setSingleOpState] = useState("");
 
     // Multi Op State
     const [multiOpType, setMultiOpType] = useState<MultiOperationType>('mean');
     const [baseSensor, setBaseSensor] = useState<string>("");
 
     const [customName, setCustomName] = useState("");
+    // Treat bad-quality samples as missing instead of flagging the result
+    const [excludeBad, setExcludeBad] = useState(false);
 
     // Update config when local state changes
     useEffect(() => {
@@ -34,9 +38,11 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
                 mode: 'single',
                 singleOp: {
                     type: singleOpType,
-                    value: singleOpValue
+                    value: singleOpValue,
+                    state: singleOpType === 'equals' && singleOpState.trim() ? singleOpState.trim() : undefined
                 },
-                customName: customName.trim() || undefined
+                customName: customName.trim() || undefined,
+                excludeBad
             });
         } else {
             // Validate multi op
@@ -54,10 +60,11 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
                     type: multiOpType,
                     baseSensor: (multiOpType === 'subtract' || multiOpType === 'divide') ? baseSensor : undefined
                 },
-                customName: customName.trim() || undefined
+                customName: customName.trim() || undefined,
+                excludeBad
             });
         }
-    }, [mode, singleOpType, singleOpValue, multiOpType, baseSensor, selectedSensors, customName, onConfigChange]);
+    }, [mode, singleOpType, singleOpValue, singleOpState, multiOpType, baseSensor, selectedSensors, customName, excludeBad, onConfigChange]);
 
     // Safety check: if baseSensor is not in selectedSensors, reset it
     useEffect(() => {
@@ -128,6 +135,17 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
                     />
                 </div>
 
+                <div className="mb-4">
+                    <label className="flex items-center gap-2 text-xs text-[var(--text-secondary)]">
+                        <input
+                            type="checkbox"
+                            checked={excludeBad}
+                            onChange={(e) => setExcludeBad(e.target.checked)}
+                        />
+                        Exclude bad-quality samples
+                    </label>
+                </div>
+
                 {mode === 'single' && (
                     <div className="flex flex-col gap-4">
                         {selectedSensors.length > 1 && (
@@ -147,8 +165,21 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
                                 <option value="multiply">Multiply (×)</option>
                                 <option value="divide">Divide (÷)</option>
                                 <option value="power">Power (^)</option>
+                                <option value="equals">Equals (=)</option>
                             </select>
                         </div>
+                        {singleOpType === 'equals' && (
+                            <div>
+                                <label className="block text-xs font-bold uppercase text-[var(--text-secondary)] mb-1">State</label>
+                                <input
+                                    type="text"
+                                    value={singleOpState}
+                                    placeholder="e.g. RUNNING (state sensors only)"
+                                    onChange={(e) => setSingleOpState(e.target.value)}
+                                    className="w-full bg-[var(--input-bg)] border border-[var(--border)] text-[var(--text-primary)] rounded p-2 text-sm focus:outline-none focus:border-[var(--accent-color)]"
+                                />
+                            </div>
+                        )}
                         <div>
                             <label className="block text-xs font-bold uppercase text-[var(--text-secondary)] mb-1">Value</label>
                             <input
@@ -164,7 +195,7 @@ export default function SensorTooling({ selectedSensors, sensorMetadata, onConfi
                             {selectedSensors.length > 0 ? (
                                 <div>
                                     {customName ? <span className="font-bold">{customName} = </span> : ''}
-                                    [Sensor] {singleOpType === 'add' ? '+' : singleOpType === 'subtract' ? '-' : singleOpType === 'multiply' ? '×' : singleOpType === 'divide' ? '÷' : '^'} {singleOpValue}
+                                    [Sensor] {singleOpType === 'add' ? '+' : singleOpType === 'subtract' ? '-' : singleOpType === 'multiply' ? '×' : singleOpType === 'divide' ? '÷' : singleOpType === 'equals' ? '=' : '^'} {singleOpType === 'equals' && singleOpState.trim() ? singleOpState.trim() : singleOpValue}
                                 </div>
                             ) : (
                                 <div className="text-[var(--text-secondary)]">Select sensors to see preview</div>
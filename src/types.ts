@@ -1,16 +1,74 @@
 export interface CsvRecord {
     timestamp: string | null;
     values: (number | null)[];
+    /** Positions in `values` of samples flagged bad. */
+    bad?: number[];
+}
+
+export type ColumnType = 'numeric' | 'boolean' | 'state';
+
+/** State table of a boolean or state sensor; its values index `states`. */
+export interface ColumnStates {
+    column: string;
+    column_type: ColumnType;
+    states: string[];
 }
 
 export interface ProcessedData {
     headers: string[];
     rows: CsvRecord[];
+    states?: ColumnStates[];
+}
+
+export interface BadCell {
+    line: number;
+    value: string;
+}
+
+export interface ColumnReport {
+    name: string;
+    empty_cells: number;
+    unparsable_cells: number;
+    bad_quality_cells: number;
+    samples: BadCell[];
+    unit: string | null;
+}
+
+export interface ClockCorrection {
+    offset_ms: number;
+    drift_span: [string, string] | null;
+    drift_start_ms: number;
+    drift_end_ms: number;
+}
+
+export interface FileReport {
+    path: string;
+    total_rows: number;
+    expected_fields: number;
+    ragged_row_count: number;
+    ragged_rows: { line: number; fields: number }[];
+    missing_timestamp_rows: number;
+    bad_quality_rows: number;
+    columns: ColumnReport[];
+    clock: ClockCorrection | null;
+}
+
+export interface ColumnConflict {
+    column: string;
+    start: string;
+    end: string;
+    conflicting_cells: number;
+}
+
+export interface IngestReport {
+    files: FileReport[];
+    conflicts: ColumnConflict[];
 }
 
 export interface CsvMetadata {
     headers: string[];
     total_rows: number;
+    report: IngestReport;
 }
 
 export interface SensorMetadata {
@@ -18,9 +76,58 @@ export interface SensorMetadata {
     description: string;
     unit: string;
     component: string;
+    /** Engineering range of the instrument. */
+    low_limit: number | null;
+    high_limit: number | null;
+    low_alarm: number | null;
+    high_alarm: number | null;
+    low_low_alarm: number | null;
+    high_high_alarm: number | null;
+    /** Expected time between samples in milliseconds. */
+    sample_period_ms: number | null;
+    data_type: ColumnType | null;
+    /** Metadata file columns other than the known fields, keyed by header. */
+    attributes: Record<string, string>;
+}
+
+/** How the loaded metadata lines up with the loaded sensors; tags match ignoring case. */
+export interface MetadataValidation {
+    missing_metadata: string[];
+    missing_data: string[];
+    duplicate_tags: string[];
+    /** Numeric sensors with samples outside the engineering range of their metadata. */
+    out_of_range: RangeViolation[];
+}
+
+/** Samples of one sensor below its low limit or above its high limit. */
+export interface RangeViolation {
+    sensor: string;
+    below_low: number;
+    above_high: number;
+    /** Timestamps of the first and last sample out of range. */
+    start: string;
+    end: string;
 }
 
-export type SingleOperationType = 'add' | 'subtract' | 'multiply' | 'divide' | 'power';
+/** Node of the equipment hierarchy built from metadata component paths. */
+export interface AssetNode {
+    name: string;
+    /** Segments from the root joined by `/`; empty for the root. */
+    path: string;
+    /** Sensors whose component is this node. */
+    sensors: string[];
+    children: AssetNode[];
+}
+
+export interface AssetSummary {
+    name: string;
+    path: string;
+    child_count: number;
+    /** Sensors at this node and below. */
+    sensor_count: number;
+}
+
+export type SingleOperationType = 'add' | 'subtract' | 'multiply' | 'divide' | 'power' | 'equals';
 export type MultiOperationType = 'sum' | 'mean' | 'median' | 'product' | 'subtract' | 'divide';
 
 export interface SensorOperationConfig {
@@ -28,10 +135,54 @@ export interface SensorOperationConfig {
     singleOp?: {
         type: SingleOperationType;
         value: number;
+        state?: string;
     };
     multiOp?: {
         type: MultiOperationType;
         baseSensor?: string;
     };
     customName?: string;
+    excludeBad?: boolean;
+}
+
+export interface ColumnSources {
+    column: string;
+    sources: string[];
+}
+
+export type ColumnKind = 'numeric' | 'boolean' | 'state' | 'text' | 'empty';
+
+export interface ColumnPreview {
+    name: string;
+    kind: ColumnKind;
+    samples: string[];
+    unit: string | null;
+}
+
+export interface CsvPreview {
+    delimiter: string;
+    headers: string[];
+    timestamp_column: string | null;
+    timestamp_format: string | null;
+    unparsed_timestamps: number;
+    rows_read: number;
+    columns: ColumnPreview[];
+}
+
+export type SearchField = 'tag' | 'description' | 'unit' | 'component';
+
+/** Characters `[start, end)` of a field matched by the search, for highlighting. */
+export interface MatchSpan {
+    field: SearchField;
+    start: number;
+    end: number;
+}
+
+export interface SensorMatch {
+    sensor: string;
+    description: string;
+    unit: string;
+    component: string;
+    score: number;
+    spans: MatchSpan[];
 }